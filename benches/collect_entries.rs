@@ -0,0 +1,63 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use jrn::{IgnorePatterns, JrnRepo, Settings, TagAliases};
+
+static DIRS: usize = 100;
+static ENTRIES_PER_DIR: usize = 200;
+
+/// Lays out a journal of DIRS * ENTRIES_PER_DIR entries, each with an attachment
+fn write_journal(root: &Path) {
+    for d in 0..DIRS {
+        let dir = root.join(format!("dir{}", d));
+        fs::create_dir(&dir).unwrap();
+        for e in 0..ENTRIES_PER_DIR {
+            let minute = e % 60;
+            let hour = (e / 60) % 24;
+            let day = d % 28 + 1;
+            let name = format!("2019-01-{:02}_{:02}{:02}-tag{}_dir{}", day, hour, minute, e % 7, d);
+            File::create(dir.join(name)).unwrap();
+            File::create(dir.join(format!("attachment{}.png", e))).unwrap();
+        }
+    }
+}
+
+fn init_with_threads(root: &Path, threads: usize) -> JrnRepo {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        JrnRepo::init_at(
+            root.to_path_buf(),
+            Settings::default(),
+            IgnorePatterns::find_or_default(),
+            TagAliases::default(),
+        )
+        .unwrap()
+    })
+}
+
+fn collect_entries(c: &mut Criterion) {
+    let dir = tempfile::Builder::new().prefix("jrn").tempdir().unwrap();
+    write_journal(dir.path());
+    let threads = num_cpus();
+
+    let mut group = c.benchmark_group("collect_entries");
+    group.sample_size(10);
+    group.bench_function("serial", |b| b.iter(|| init_with_threads(dir.path(), 1)));
+    group.bench_function(format!("parallel_{}", threads), |b| {
+        b.iter(|| init_with_threads(dir.path(), threads))
+    });
+    group.finish();
+}
+
+fn num_cpus() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+criterion_group!(benches, collect_entries);
+criterion_main!(benches);
@@ -0,0 +1,533 @@
+//! An interactive terminal browser of the entries in a repo
+//!
+//! Entries are listed newest first beside a preview of the selected entry.
+//! The filter narrows the list as it is typed, words starting with # match tags,
+//! any other word must appear in the name or text of the entry
+//!     #work standup
+//! The text of an entry is only read once it is shown, or searched by the filter
+//! Keys
+//!     j k ↑ ↓      move the selection, g G to the first and last entry
+//!     J K          scroll the preview
+//!     /            edit the filter, enter keeps it and esc clears it
+//!     e enter      open the entry in the editor
+//!     t            push a tag to the entry
+//!     T            remove a tag from the entry
+//!     d            delete the entry, after confirmation
+//!     q esc        quit
+
+use std::collections::HashMap;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use jrn::{tag, JrnEntry, JrnError, JrnRepo, TagAliases};
+
+use crate::print;
+
+static HELP: &str = "/ filter  e edit  t tag  T untag  d delete  q quit";
+/// the number of entries moved by page up and page down
+static PAGE: usize = 10;
+
+/// Runs the browser until it is quit, the terminal is restored even if an operation fails
+pub(crate) fn run(repo: &mut JrnRepo) -> Result<(), JrnError> {
+    if !io::stdout().is_terminal() {
+        let e = io::Error::new(io::ErrorKind::Unsupported, "browse needs a terminal");
+        return Err(JrnError::IO(e));
+    }
+    let mut browser = Browser::new(rows(repo), print::color(repo));
+    let mut terminal = ratatui::try_init()?;
+    let result = browser.event_loop(&mut terminal, repo);
+    ratatui::try_restore()?;
+    result
+}
+
+/// An entry as shown in the browser
+#[derive(Debug)]
+struct Row {
+    path: PathBuf,
+    time: String,
+    tags: Vec<String>,
+    /// the name of the entry in lower case, searched by the filter
+    name: String,
+    /// None until the text is read, see [Browser::load]
+    text: Option<Text>,
+}
+
+/// What the browser shows and searches of the text of an entry
+#[derive(Debug)]
+struct Text {
+    subject: String,
+    preview: String,
+    /// the text in lower case, searched by the filter
+    search: String,
+}
+
+impl Row {
+    fn of(entry: &JrnEntry, repo: &JrnRepo) -> Self {
+        Row {
+            path: entry.file_path.clone(),
+            time: entry.creation_time.in_zone(repo.get_display_zone()).to_string(),
+            tags: entry.tags.clone(),
+            name: entry.file_name(repo).to_lowercase(),
+            text: None,
+        }
+    }
+
+    /// true if the row satisfies every word of filter, see the module docs
+    fn matches(&self, filter: &str, aliases: &TagAliases) -> bool {
+        filter.split_whitespace().all(|word| match word.strip_prefix('#') {
+            Some("") => true,
+            Some(query) => self.tags.iter().any(|t| tag::matches(t, &aliases.normalize(query))),
+            None => {
+                let word = word.to_lowercase();
+                let in_text = self.text.as_ref().is_some_and(|text| text.search.contains(&word));
+                self.name.contains(&word) || in_text
+            }
+        })
+    }
+}
+
+impl Text {
+    fn of(entry: &JrnEntry, repo: &JrnRepo) -> Self {
+        let body = entry.body_or_error(repo);
+        let subject = body.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+        let metadata = entry.display(repo).body(false).color(false).to_string();
+        Text {
+            subject: String::from(subject.trim_start_matches('#').trim()),
+            search: body.to_lowercase(),
+            preview: metadata + &body,
+        }
+    }
+}
+
+/// The rows of every entry in repo, newest first, without their text
+fn rows(repo: &JrnRepo) -> Vec<Row> {
+    repo.entries().iter().rev().map(|entry| Row::of(entry, repo)).collect()
+}
+
+/// What keys are currently being read as
+#[derive(Debug, PartialEq)]
+enum Mode {
+    List,
+    Filter,
+    PushTag(String),
+    RemoveTag(String),
+    ConfirmDelete,
+}
+
+/// An operation on the repo requested by a key, entries are named by their path
+#[derive(Debug, PartialEq)]
+enum Action {
+    Quit,
+    Edit(PathBuf),
+    PushTag(PathBuf, String),
+    RemoveTag(PathBuf, String),
+    Delete(PathBuf),
+}
+
+impl Action {
+    /// The entry acted on, None for quit
+    fn path(&self) -> Option<&Path> {
+        match self {
+            Action::Quit => None,
+            Action::Edit(path)
+            | Action::PushTag(path, _)
+            | Action::RemoveTag(path, _)
+            | Action::Delete(path) => Some(path),
+        }
+    }
+}
+
+struct Browser {
+    rows: Vec<Row>,
+    filter: String,
+    /// indices into rows of the entries matching the filter
+    visible: Vec<usize>,
+    list: ListState,
+    preview_scroll: u16,
+    mode: Mode,
+    /// the outcome of the last operation, shown until the next key
+    status: String,
+    color: bool,
+}
+
+impl Browser {
+    fn new(rows: Vec<Row>, color: bool) -> Self {
+        let mut browser = Browser {
+            visible: (0..rows.len()).collect(),
+            rows,
+            filter: String::new(),
+            list: ListState::default(),
+            preview_scroll: 0,
+            mode: Mode::List,
+            status: String::new(),
+            color,
+        };
+        browser.select(0);
+        browser
+    }
+
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        repo: &mut JrnRepo,
+    ) -> Result<(), JrnError> {
+        loop {
+            self.load(repo, self.shown(terminal.size()?.height as usize));
+            terminal.draw(|frame| self.draw(frame))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            self.status.clear();
+            let action = self.handle(key, repo.tag_aliases());
+            // the filter searches the text of every entry
+            if self.mode == Mode::Filter && self.rows.iter().any(|row| row.text.is_none()) {
+                self.load(repo, 0..self.rows.len());
+                if !self.filter.is_empty() {
+                    self.refilter(repo.tag_aliases());
+                }
+            }
+            let action = match action {
+                Some(action) => action,
+                None => continue,
+            };
+
+            let result = match &action {
+                Action::Quit => return Ok(()),
+                Action::Edit(path) => {
+                    // the editor needs the terminal to itself
+                    ratatui::try_restore()?;
+                    let result = repo.edit_entry(&path.to_string_lossy());
+                    *terminal = ratatui::try_init()?;
+                    result
+                }
+                Action::PushTag(path, tag) => {
+                    repo.push_tag(tag, Some(&path.to_string_lossy()), |_| Ok(true))
+                }
+                Action::RemoveTag(path, tag) => repo.remove_tag(tag, Some(&path.to_string_lossy())),
+                // the deletion was confirmed in the browser
+                Action::Delete(path) => repo.remove_entry(&path.to_string_lossy(), |_| Ok(true)),
+            };
+            // anything the operation wrote is drawn over
+            terminal.clear()?;
+            let changed = action.path().map(Path::to_path_buf);
+            self.status = match (result, action) {
+                (Err(e), _) => e.to_string(),
+                (Ok(()), Action::Delete(path)) => {
+                    format!("moved {} to the trash, jrn undo restores it", path.display())
+                }
+                (Ok(()), _) => String::new(),
+            };
+            self.reload(rows(repo), changed.as_deref(), repo.tag_aliases());
+        }
+    }
+
+    /// Reads the text of the rows at indices that have not been read yet
+    fn load(&mut self, repo: &JrnRepo, indices: impl IntoIterator<Item = usize>) {
+        let mut unread: HashMap<&Path, usize> = HashMap::new();
+        for i in indices {
+            if self.rows[i].text.is_none() {
+                unread.insert(&self.rows[i].path, i);
+            }
+        }
+        let texts: Vec<_> = repo
+            .entries()
+            .iter()
+            .filter_map(|entry| {
+                let i = *unread.get(entry.file_path.as_path())?;
+                Some((i, Text::of(entry, repo)))
+            })
+            .collect();
+        for (i, text) in texts {
+            self.rows[i].text = Some(text);
+        }
+    }
+
+    /// The indices into rows of the entries that may be shown in a terminal of height
+    ///
+    /// The list scrolls to keep the selection in view, so this covers a screen on either side of it
+    fn shown(&self, height: usize) -> Vec<usize> {
+        let selected = self.list.selected().unwrap_or(0);
+        let start = selected.saturating_sub(height).min(self.list.offset());
+        let end = selected.max(self.list.offset()) + height;
+        self.visible.iter().skip(start).take(end - start).copied().collect()
+    }
+
+    /// Replaces the rows after the entry at changed was acted on, keeping the selected entry
+    /// if it is still there
+    ///
+    /// Text already read is kept, except that of the changed entry and of entries with links,
+    /// which may have been rewritten
+    fn reload(&mut self, mut rows: Vec<Row>, changed: Option<&Path>, aliases: &TagAliases) {
+        let selected = self.selected().map(|row| row.path.clone());
+        let position = self.list.selected().unwrap_or(0);
+        let mut texts: HashMap<PathBuf, Text> = self.rows
+            .drain(..)
+            .filter_map(|row| Some((row.path, row.text?)))
+            .filter(|(path, text)| Some(path.as_path()) != changed && !text.search.contains("[["))
+            .collect();
+        for row in &mut rows {
+            row.text = texts.remove(&row.path);
+        }
+        self.rows = rows;
+        self.refilter(aliases);
+        let index = selected
+            .and_then(|path| self.visible.iter().position(|&i| self.rows[i].path == path))
+            .unwrap_or(position);
+        self.select(index);
+    }
+
+    /// Applies the filter to every row, the selection returns to the newest entry
+    fn refilter(&mut self, aliases: &TagAliases) {
+        let filter = &self.filter;
+        self.visible = (0..self.rows.len())
+            .filter(|&i| self.rows[i].matches(filter, aliases))
+            .collect();
+        self.select(0);
+    }
+
+    /// Selects the visible row at index, or the last one if index is past the end
+    fn select(&mut self, index: usize) {
+        self.preview_scroll = 0;
+        match self.visible.len() {
+            0 => self.list.select(None),
+            len => self.list.select(Some(index.min(len - 1))),
+        }
+    }
+
+    fn selected(&self) -> Option<&Row> {
+        self.list.selected().and_then(|i| self.visible.get(i)).map(|&i| &self.rows[i])
+    }
+
+    /// Updates the browser for key, returning the operation it asks for if any
+    fn handle(&mut self, key: KeyEvent, aliases: &TagAliases) -> Option<Action> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+
+        match &mut self.mode {
+            Mode::List => return self.handle_list(key, aliases),
+            Mode::Filter => {
+                match key.code {
+                    KeyCode::Char(c) => self.filter.push(c),
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    }
+                    KeyCode::Enter => self.mode = Mode::List,
+                    KeyCode::Esc => {
+                        self.filter.clear();
+                        self.mode = Mode::List;
+                    }
+                    _ => return None,
+                }
+                // the filter is applied as it is typed
+                self.refilter(aliases);
+            }
+            Mode::PushTag(input) | Mode::RemoveTag(input) => match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    let tag = String::from(input.trim());
+                    let path = self.selected().map(|row| row.path.clone());
+                    let mode = std::mem::replace(&mut self.mode, Mode::List);
+                    return match (path, mode) {
+                        (Some(path), Mode::PushTag(_)) if !tag.is_empty() => {
+                            Some(Action::PushTag(path, tag))
+                        }
+                        (Some(path), Mode::RemoveTag(_)) if !tag.is_empty() => {
+                            Some(Action::RemoveTag(path, tag))
+                        }
+                        _ => None,
+                    };
+                }
+                KeyCode::Esc => self.mode = Mode::List,
+                _ => (),
+            },
+            Mode::ConfirmDelete => {
+                self.mode = Mode::List;
+                return match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        self.selected().map(|row| Action::Delete(row.path.clone()))
+                    }
+                    _ => {
+                        self.status = String::from("not deleted");
+                        None
+                    }
+                };
+            }
+        }
+        None
+    }
+
+    fn handle_list(&mut self, key: KeyEvent, aliases: &TagAliases) -> Option<Action> {
+        let position = self.list.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Char('q') => return Some(Action::Quit),
+            KeyCode::Esc if self.filter.is_empty() => return Some(Action::Quit),
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.refilter(aliases);
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.select(position + 1),
+            KeyCode::Char('k') | KeyCode::Up => self.select(position.saturating_sub(1)),
+            KeyCode::PageDown => self.select(position + PAGE),
+            KeyCode::PageUp => self.select(position.saturating_sub(PAGE)),
+            KeyCode::Char('g') | KeyCode::Home => self.select(0),
+            KeyCode::Char('G') | KeyCode::End => self.select(usize::MAX),
+            KeyCode::Char('J') => self.preview_scroll = self.preview_scroll.saturating_add(1),
+            KeyCode::Char('K') => self.preview_scroll = self.preview_scroll.saturating_sub(1),
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('e') | KeyCode::Enter => {
+                return self.selected().map(|row| Action::Edit(row.path.clone()))
+            }
+            KeyCode::Char('t') if self.selected().is_some() => {
+                self.mode = Mode::PushTag(String::new())
+            }
+            KeyCode::Char('T') if self.selected().is_some() => {
+                self.mode = Mode::RemoveTag(String::new())
+            }
+            KeyCode::Char('d') if self.selected().is_some() => self.mode = Mode::ConfirmDelete,
+            _ => (),
+        }
+        None
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, bottom] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, preview_area] =
+            Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)])
+                .areas(main);
+
+        let (rows, color) = (&self.rows, self.color);
+        let style = |fg| match color {
+            true => Style::new().fg(fg),
+            false => Style::new(),
+        };
+        let items: Vec<ListItem> = self.visible.iter().map(|&i| {
+            let row = &rows[i];
+            ListItem::new(Line::from(vec![
+                Span::styled(row.time.as_str(), style(Color::Green)),
+                Span::raw(" "),
+                Span::styled(row.tags.join(" "), style(Color::Cyan)),
+                Span::raw(" "),
+                Span::raw(row.text.as_ref().map_or("", |text| text.subject.as_str())),
+            ]))
+        }).collect();
+        let title = format!(" {} of {} entries ", self.visible.len(), self.rows.len());
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+
+        let preview = self.selected()
+            .and_then(|row| row.text.as_ref())
+            .map_or("", |text| text.preview.as_str());
+        let preview = Paragraph::new(preview)
+            .block(Block::bordered())
+            .wrap(Wrap { trim: false })
+            .scroll((self.preview_scroll, 0));
+        frame.render_widget(preview, preview_area);
+
+        let name = self
+            .selected()
+            .and_then(|row| row.path.file_name())
+            .map(|n| n.to_string_lossy());
+        let line = match &self.mode {
+            Mode::List if !self.status.is_empty() => self.status.clone(),
+            Mode::List if !self.filter.is_empty() => format!("/{}    {}", self.filter, HELP),
+            Mode::List => String::from(HELP),
+            Mode::Filter => format!("/{}", self.filter),
+            Mode::PushTag(input) => format!("push tag: {}", input),
+            Mode::RemoveTag(input) => format!("remove tag: {}", input),
+            Mode::ConfirmDelete => format!("delete {}? y/n", name.unwrap_or_default()),
+        };
+        frame.render_widget(Paragraph::new(line), bottom);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn row(name: &str, tags: &[&str], body: &str) -> Row {
+        Row {
+            path: PathBuf::from(name),
+            time: String::new(),
+            tags: tags.iter().map(|t| String::from(*t)).collect(),
+            name: String::from(name),
+            text: Some(Text {
+                subject: String::new(),
+                preview: String::new(),
+                search: body.to_lowercase(),
+            }),
+        }
+    }
+
+    fn press(browser: &mut Browser, keys: &str) -> Option<Action> {
+        let aliases = TagAliases::default();
+        keys.chars()
+            .map(|c| browser.handle(KeyEvent::from(KeyCode::Char(c)), &aliases))
+            .last()
+            .flatten()
+    }
+
+    fn browser() -> Browser {
+        Browser::new(vec![
+            row("c", &["work/projectx"], "Standup notes"),
+            row("b", &["home"], "standup with the cat"),
+            row("a", &["work"], "planning"),
+        ], false)
+    }
+
+    #[test]
+    fn filters_by_tag_and_text() {
+        let mut browser = browser();
+        press(&mut browser, "/#work");
+        assert_eq!(browser.visible, vec![0, 2]);
+        press(&mut browser, " standup");
+        assert_eq!(browser.visible, vec![0]);
+
+        browser.handle(KeyEvent::from(KeyCode::Esc), &TagAliases::default());
+        assert_eq!(browser.visible, vec![0, 1, 2]);
+        assert_eq!(browser.mode, Mode::List);
+    }
+
+    #[test]
+    fn deletes_after_confirmation() {
+        let mut browser = browser();
+        assert_eq!(press(&mut browser, "jdn"), None);
+        assert_eq!(press(&mut browser, "dy"), Some(Action::Delete(PathBuf::from("b"))));
+    }
+
+    #[test]
+    fn reads_tags_to_push() {
+        let mut browser = browser();
+        press(&mut browser, "tidea");
+        let action = browser.handle(KeyEvent::from(KeyCode::Enter), &TagAliases::default());
+        assert_eq!(action, Some(Action::PushTag(PathBuf::from("c"), String::from("idea"))));
+    }
+
+    #[test]
+    fn reload_keeps_text_of_unchanged_entries() {
+        let mut browser = Browser::new(vec![
+            row("c", &[], "see [[a]]"),
+            row("b", &[], "edited"),
+            row("a", &[], "unchanged"),
+        ], false);
+        let unread = |name: &str| Row { text: None, ..row(name, &[], "") };
+        let rows = vec![unread("c"), unread("b"), unread("a")];
+        browser.reload(rows, Some(Path::new("b")), &TagAliases::default());
+        let read: Vec<bool> = browser.rows.iter().map(|row| row.text.is_some()).collect();
+        assert_eq!(read, vec![false, false, true]);
+    }
+}
@@ -0,0 +1,151 @@
+//! A month calendar of entries, laid out like cal
+//!
+//! Days with entries are highlighted and carry their number of entries
+//!            October 2019
+//!     Mo   Tu   We   Th   Fr   Sa   Su
+//!           1²   2    3¹²  4    5    6
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::color::{paint, Style};
+
+static MONTHS: &[&str] = &[
+    "january", "february", "march", "april", "may", "june",
+    "july", "august", "september", "october", "november", "december",
+];
+static SUPERSCRIPTS: &[char] = &['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+/// the width of a day in the grid, its number, a count of up to two digits and a space
+static CELL_WIDTH: usize = 5;
+
+/// Parses a month given on the command line, the current month if none is given
+///
+/// Accepts 2019-10, 10 or a month name such as oct, months without a year are in the year of today
+pub(crate) fn parse_month(input: Option<&str>, today: NaiveDate) -> Option<(i32, u32)> {
+    let input = match input.map(str::trim) {
+        None | Some("") => return Some((today.year(), today.month())),
+        Some(input) => input.to_lowercase(),
+    };
+
+    let (year, month) = match input.split_once('-') {
+        Some((year, month)) => (year.parse().ok()?, month.parse().ok()?),
+        None => match input.parse::<u32>() {
+            Ok(month) => (today.year(), month),
+            Err(_) if input.len() >= 3 => {
+                let month = MONTHS.iter().position(|name| name.starts_with(&input))?;
+                (today.year(), month as u32 + 1)
+            }
+            Err(_) => return None,
+        },
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).map(|_| (year, month))
+}
+
+/// The number of entries written on each day of a month
+#[derive(Debug, PartialEq)]
+pub struct Calendar {
+    pub year: i32,
+    pub month: u32,
+    /// the days entries were written on and their number of entries
+    pub counts: BTreeMap<NaiveDate, usize>,
+}
+
+impl Calendar {
+    /// Writes the grid of the month, with the number of entries of each day
+    pub fn write<W: Write>(&self, f: &mut W, color: bool) -> fmt::Result {
+        let first = NaiveDate::from_ymd_opt(self.year, self.month, 1).ok_or(fmt::Error)?;
+        let title = first.format("%B %Y").to_string();
+        let width = CELL_WIDTH * 7;
+        writeln!(f, "{}", format!("{:^width$}", title, width = width).trim_end())?;
+        writeln!(f, "{}", paint("Mo   Tu   We   Th   Fr   Sa   Su", Style::Header, color))?;
+
+        let mut line = " ".repeat(CELL_WIDTH * first.weekday().num_days_from_monday() as usize);
+        let mut day = first;
+        while day.month() == self.month {
+            let count = self.counts.get(&day).copied().unwrap_or(0);
+            let number = format!("{:>2}", day.day());
+            let mark = superscript(count);
+            match count {
+                0 => line.push_str(&number),
+                _ => {
+                    let number = paint(number, Style::Time, color);
+                    write!(line, "{}{}", number, paint(&mark, Style::Id, color))?
+                }
+            }
+            line.push_str(&" ".repeat(CELL_WIDTH - 2 - mark.chars().count()));
+
+            if day.weekday().num_days_from_monday() == 6 {
+                writeln!(f, "{}", line.trim_end())?;
+                line.clear();
+            }
+            day = day.succ_opt().expect("day before the end of time");
+        }
+        if !line.trim().is_empty() {
+            writeln!(f, "{}", line.trim_end())?;
+        }
+
+        let month_counts = self
+            .counts
+            .iter()
+            .filter(|(d, _)| d.year() == self.year && d.month() == self.month);
+        let (entries, days) = month_counts
+            .fold((0, 0), |(entries, days), (_, count)| (entries + count, days + 1));
+        writeln!(f, "\n{} entries on {} days", entries, days)
+    }
+}
+
+/// count in superscript digits, nothing for no entries and ⁺ past 99
+fn superscript(count: usize) -> String {
+    match count {
+        0 => String::new(),
+        1..=99 => count
+            .to_string()
+            .chars()
+            .map(|c| SUPERSCRIPTS[c as usize - '0' as usize])
+            .collect(),
+        _ => String::from("⁺"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_months() {
+        let today = NaiveDate::from_ymd_opt(2019, 10, 17).unwrap();
+        assert_eq!(parse_month(None, today), Some((2019, 10)));
+        assert_eq!(parse_month(Some("2020-02"), today), Some((2020, 2)));
+        assert_eq!(parse_month(Some("3"), today), Some((2019, 3)));
+        assert_eq!(parse_month(Some("Sept"), today), Some((2019, 9)));
+        assert_eq!(parse_month(Some("13"), today), None);
+        assert_eq!(parse_month(Some("ma"), today), None);
+    }
+
+    #[test]
+    fn writes_grid() {
+        let mut counts = BTreeMap::new();
+        counts.insert(NaiveDate::from_ymd_opt(2019, 10, 1).unwrap(), 2);
+        counts.insert(NaiveDate::from_ymd_opt(2019, 10, 31).unwrap(), 12);
+        let mut out = String::new();
+        Calendar { year: 2019, month: 10, counts }.write(&mut out, false).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0].trim(), "October 2019");
+        assert_eq!(lines[2], "      1²   2    3    4    5    6");
+        assert_eq!(lines[6], "28   29   30   31¹²");
+        assert_eq!(lines[8], "14 entries on 2 days");
+    }
+
+    #[test]
+    fn separates_days_with_many_entries() {
+        let mut counts = BTreeMap::new();
+        for (day, count) in [(1, 10), (2, 99), (3, 100)] {
+            counts.insert(NaiveDate::from_ymd_opt(2019, 10, day).unwrap(), count);
+        }
+        let mut out = String::new();
+        Calendar { year: 2019, month: 10, counts }.write(&mut out, false).unwrap();
+        assert_eq!(out.lines().nth(2), Some("      1¹⁰  2⁹⁹  3⁺   4    5    6"));
+    }
+}
@@ -0,0 +1,121 @@
+//! ANSI colours for terminal output, in the style of git log
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::JrnError;
+
+/// When output is coloured
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ColorChoice {
+    /// only when writing to a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Decides whether to colour output going to a terminal or not
+    ///
+    /// Auto also respects the NO_COLOR convention and dumb terminals
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                is_terminal
+                    && std::env::var_os("NO_COLOR").is_none()
+                    && std::env::var("TERM").map(|t| t != "dumb").unwrap_or(true)
+            }
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = JrnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(JrnError::InvalidColorChoice(String::from(s))),
+        }
+    }
+}
+
+/// The colours used for each part of an entry
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Style {
+    Header,
+    Id,
+    Time,
+    Tag,
+    Location,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Header => "2",
+            Style::Id => "33",
+            Style::Time => "32",
+            Style::Tag => "36",
+            Style::Location => "34",
+        }
+    }
+}
+
+/// Displays value in style, or plainly if colour is disabled
+pub struct Painted<T> {
+    value: T,
+    style: Style,
+    enabled: bool,
+}
+
+pub fn paint<T: Display>(value: T, style: Style, enabled: bool) -> Painted<T> {
+    Painted { value, style, enabled }
+}
+
+impl<T: Display> Display for Painted<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.enabled {
+            write!(f, "\x1b[{}m{}\x1b[0m", self.style.code(), self.value)
+        } else {
+            write!(f, "{}", self.value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_choices() {
+        assert_eq!("Always".parse::<ColorChoice>().unwrap(), ColorChoice::Always);
+        assert_eq!(" never".parse::<ColorChoice>().unwrap(), ColorChoice::Never);
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
+
+    #[test]
+    fn never_colours_when_disabled() {
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(ColorChoice::Always.enabled(false));
+        assert!(!ColorChoice::Auto.enabled(false));
+    }
+
+    #[test]
+    fn paints_with_ansi_codes() {
+        assert_eq!(paint("abc", Style::Id, true).to_string(), "\x1b[33mabc\x1b[0m");
+        assert_eq!(paint("abc", Style::Id, false).to_string(), "abc");
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{self, Write};
+
+use clap::Shell;
+
+/// number of entry ids offered when completing an entry
+pub static RECENT_ENTRIES: usize = 20;
+
+/// Writes the completion script for shell to out
+///
+/// The static script generated by clap is extended to complete tags and entry ids
+/// by calling back into `jrn __complete`
+pub fn write_script<W: Write>(mut app: clap::App, shell: Shell, out: &mut W) -> io::Result<()> {
+    let mut script: Vec<u8> = Vec::new();
+    app.gen_completions_to("jrn", shell, &mut script);
+    let script = String::from_utf8_lossy(&script);
+
+    match shell {
+        Shell::Bash => {
+            write!(out, "{}", script)?;
+            write!(out, "{}", BASH_DYNAMIC)
+        }
+        Shell::Zsh => {
+            // the generated script ends by calling _jrn, call the dynamic wrapper instead
+            let script = script.trim_end();
+            let script = script.strip_suffix("_jrn \"$@\"").unwrap_or(script);
+            write!(out, "{}", script)?;
+            write!(out, "{}", ZSH_DYNAMIC)
+        }
+        Shell::Fish => {
+            write!(out, "{}", script)?;
+            write!(out, "{}", FISH_DYNAMIC)
+        }
+        _ => write!(out, "{}", script),
+    }
+}
+
+static BASH_DYNAMIC: &str = r#"
+_jrn_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    local kind=""
+    if [[ "$cur" != -* && $COMP_CWORD -gt 1 ]]; then
+        case "$prev" in
+            -t|--tag) kind="tags" ;;
+            -*) ;;
+            *)
+                case "${COMP_WORDS[1]}" in
+                    new|push-tag|pt) kind="tags" ;;
+                    show|edit|remove|rm|retime|links|backlinks) kind="entries" ;;
+                    attach) [[ $COMP_CWORD -eq 2 ]] && kind="entries" ;;
+                esac
+                ;;
+        esac
+    fi
+    if [[ -n "$kind" ]]; then
+        COMPREPLY=( $(compgen -W "$(jrn __complete $kind 2>/dev/null)" -- "$cur") )
+        return 0
+    fi
+    _jrn "$@"
+}
+
+complete -F _jrn_dynamic -o bashdefault -o default jrn
+"#;
+
+static ZSH_DYNAMIC: &str = r#"
+
+_jrn_dynamic() {
+    local kind=""
+    if [[ $CURRENT -gt 2 && ${words[CURRENT]} != -* ]]; then
+        case ${words[CURRENT-1]} in
+            -t|--tag) kind="tags" ;;
+            -*) ;;
+            *)
+                case ${words[2]} in
+                    new|push-tag|pt) kind="tags" ;;
+                    show|edit|remove|rm|retime|links|backlinks) kind="entries" ;;
+                    attach) [[ $CURRENT -eq 3 ]] && kind="entries" ;;
+                esac
+                ;;
+        esac
+    fi
+    if [[ -n $kind ]]; then
+        compadd -- ${(f)"$(jrn __complete $kind 2>/dev/null)"}
+        return
+    fi
+    _jrn "$@"
+}
+
+_jrn_dynamic "$@"
+"#;
+
+static FISH_DYNAMIC: &str = r#"
+complete -c jrn -n "__fish_seen_subcommand_from new push-tag pt" \
+    -f -a "(jrn __complete tags 2>/dev/null)"
+complete -c jrn -n "__fish_prev_arg_in -t --tag" -f -a "(jrn __complete tags 2>/dev/null)"
+complete -c jrn -n "__fish_seen_subcommand_from show edit remove rm retime links backlinks" \
+    -f -a "(jrn __complete entries 2>/dev/null)"
+complete -c jrn -n "__fish_seen_subcommand_from attach; and test (count (commandline -opc)) -eq 2" \
+    -a "(jrn __complete entries 2>/dev/null)"
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn script(shell: Shell) -> String {
+        let app = clap::App::new("jrn").subcommand(clap::SubCommand::with_name("new"));
+        let mut out: Vec<u8> = Vec::new();
+        write_script(app, shell, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn zsh_calls_dynamic_wrapper() {
+        let script = script(Shell::Zsh);
+        assert!(script.trim_end().ends_with("_jrn_dynamic \"$@\""));
+        assert_eq!(script.matches("\n_jrn \"$@\"").count(), 0);
+    }
+
+    #[test]
+    fn bash_registers_dynamic_wrapper() {
+        let script = script(Shell::Bash);
+        let registration = "complete -F _jrn_dynamic -o bashdefault -o default jrn";
+        assert!(script.trim_end().ends_with(registration));
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use log::warn;
+
+use super::Settings;
+
+/// Rules used to bring tags into their canonical form
+///
+/// Tags are trimmed, optionally case folded, and then mapped through
+/// the aliases found in the .jrnaliases file. Each line of the file maps an alias
+/// to its canonical tag, lines starting with # are ignored
+///     mtg = meeting
+///     Meeting = meeting
+#[derive(Debug, Default)]
+pub struct TagAliases {
+    case_fold: bool,
+    aliases: HashMap<String, String>,
+}
+
+impl TagAliases {
+    /// find an aliases file in the current dir
+    /// case folding is enabled by the TagCaseFold setting
+    ///
+    /// will log warnings but not fail for IO or formatting Errors
+    pub fn find_or_default(config: &Settings) -> Self {
+        TagAliases::find_or_default_in(&std::env::current_dir().unwrap_or_default(), config)
+    }
+
+    /// find an aliases file in the repo at root, see [TagAliases::find_or_default]
+    pub fn find_or_default_in(root: &Path, config: &Settings) -> Self {
+        let mut result = TagAliases {
+            case_fold: config.get_tag_case_fold(),
+            aliases: HashMap::new(),
+        };
+
+        result.read(&root.join(super::JRN_ALIASES_FILE_NAME));
+        result
+    }
+
+    /// Returns the canonical form of tag
+    pub fn normalize(&self, tag: &str) -> String {
+        let tag = self.fold(tag);
+        match self.aliases.get(&tag) {
+            Some(canonical) => canonical.clone(),
+            None => tag,
+        }
+    }
+
+    /// Normalizes every tag, dropping empty tags and duplicates while keeping order
+    pub fn normalize_all<S: AsRef<str>>(&self, tags: &[S]) -> Vec<String> {
+        let mut result: Vec<String> = Vec::with_capacity(tags.len());
+        for tag in tags {
+            let tag = self.normalize(tag.as_ref());
+            if !tag.is_empty() && !result.contains(&tag) {
+                result.push(tag);
+            }
+        }
+        result
+    }
+
+    pub fn insert(&mut self, alias: &str, canonical: &str) {
+        let alias = self.fold(alias);
+        let canonical = self.fold(canonical);
+        self.aliases.insert(alias, canonical);
+    }
+
+    fn fold(&self, tag: &str) -> String {
+        let tag = tag.trim();
+        if self.case_fold {
+            tag.to_lowercase()
+        } else {
+            String::from(tag)
+        }
+    }
+
+    /// read aliases from a path
+    /// does nothing if no file is found at path
+    ///
+    /// warns and skips when
+    ///     file cannot be read
+    ///     a line is not formatted as alias = canonical
+    fn read(&mut self, path: &Path) {
+        if !path.exists() {
+            return;
+        }
+
+        let mut buf = String::new();
+        match File::open(path).and_then(|mut file| file.read_to_string(&mut buf)) {
+            Ok(_) => {
+                for line in buf.lines().map(str::trim) {
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    match line.find('=') {
+                        Some(i) => self.insert(&line[..i], &line[i + 1..]),
+                        None => {
+                            warn!("Skipping malformed alias \"{}\" in {}", line, path.display())
+                        }
+                    }
+                }
+            }
+            Err(_) => warn!("Can not read aliases file: {}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn aliases(case_fold: bool) -> TagAliases {
+        let mut aliases = TagAliases {
+            case_fold,
+            aliases: HashMap::new(),
+        };
+        aliases.insert("mtg", "meeting");
+        aliases
+    }
+
+    #[test]
+    fn trims() {
+        assert_eq!(aliases(false).normalize("  work "), "work");
+    }
+
+    #[test]
+    fn case_folds_when_enabled() {
+        assert_eq!(aliases(true).normalize("Meeting"), "meeting");
+        assert_eq!(aliases(false).normalize("Meeting"), "Meeting");
+    }
+
+    #[test]
+    fn maps_aliases() {
+        assert_eq!(aliases(true).normalize("MTG"), "meeting");
+        assert_eq!(aliases(false).normalize("mtg"), "meeting");
+    }
+
+    #[test]
+    fn normalize_all_dedups() {
+        let tags = aliases(true).normalize_all(&["mtg", "Meeting", "work", " "]);
+        assert_eq!(tags, vec!["meeting", "work"]);
+    }
+
+    #[test]
+    fn reads_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".jrnaliases");
+        let text = "# comment\nmtg = meeting\nmalformed\n\np:alice=person:alice\n";
+        std::fs::write(&path, text).unwrap();
+
+        let mut aliases = TagAliases::default();
+        aliases.read(&path);
+        assert_eq!(aliases.normalize("mtg"), "meeting");
+        assert_eq!(aliases.normalize("p:alice"), "person:alice");
+        assert_eq!(aliases.normalize("malformed"), "malformed");
+    }
+}
@@ -17,7 +17,9 @@ pub struct IgnorePatterns {
 impl Default for IgnorePatterns {
     fn default() -> Self {
         let mut filters = HashSet::new();
-        filters.insert(String::from("\\..*"));
+        filters.insert(String::from("^\\."));
+        // keeps the trash out of the entries should the hidden file pattern change
+        filters.insert(format!("^{}$", regex::escape(super::JRN_DIR)));
         IgnorePatterns {
             filters,
             initialized: false,
@@ -32,16 +34,13 @@ impl IgnorePatterns {
     ///
     /// will log warnings but not fail for IO or Regex Errors
     pub fn find_or_default() -> Self {
-        let mut result = IgnorePatterns::default();
-
-        //check the current dir for ignore file
-        let current_dir = std::env::current_dir().ok();
-        if let Some(mut dir) = current_dir {
-            dir.push(super::JRN_IGNORE_FILE_NAME);
-            let found = IgnorePatterns::from_path(&dir);
-            result = result.merge(found);
-        }
+        IgnorePatterns::find_or_default_in(&std::env::current_dir().unwrap_or_default())
+    }
 
+    /// find a Ignore file in the repo at root, see [IgnorePatterns::find_or_default]
+    pub fn find_or_default_in(root: &Path) -> Self {
+        let found = IgnorePatterns::from_path(&root.join(super::JRN_IGNORE_FILE_NAME));
+        let mut result = IgnorePatterns::default().merge(found);
         result.init_regex();
         result
     }
@@ -75,7 +74,7 @@ impl IgnorePatterns {
         let mut result = IgnorePatterns::empty();
 
         if path.exists() {
-            if let Ok(mut file) = File::open(&path) {
+            if let Ok(mut file) = File::open(path) {
                 let mut buf: String = String::new();
                 if file.read_to_string(&mut buf).is_ok() {
                     result.filters = buf.lines().map(String::from).collect();
@@ -126,6 +125,18 @@ impl IgnorePatterns {
 mod test {
     use super::*;
 
+    #[test]
+    fn default_keeps_extensions() {
+        let default = IgnorePatterns::find_or_default();
+        assert!(!default.matches(Path::new("2019-10-01_0930-work.age")));
+    }
+
+    #[test]
+    fn default_ignores_trash() {
+        let default = IgnorePatterns::find_or_default();
+        assert!(default.matches(Path::new(".jrn")));
+    }
+
     #[test]
     fn default_ignores_git() {
         let path = Path::new(".git");
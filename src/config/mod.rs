@@ -1,10 +1,15 @@
+mod aliases;
 mod ignore;
 mod settings;
 
 //exports
+pub use aliases::TagAliases;
 pub use ignore::IgnorePatterns;
-pub use settings::Settings;
+pub use settings::{JrnSetting, Settings};
 
 //statics
 static JRN_CONFIG_FILE_NAME: &str = ".jrnconfig";
 static JRN_IGNORE_FILE_NAME: &str = ".jrnignore";
+static JRN_ALIASES_FILE_NAME: &str = ".jrnaliases";
+/// the directory at the root of a repo holding its undo log and trash
+pub(crate) static JRN_DIR: &str = ".jrn";
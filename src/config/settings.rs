@@ -5,13 +5,23 @@ use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::OnceLock;
 use crate::*;
+use crate::crypt::Keys;
+
+static IDENTITY_FILE_NAME: &str = "identity.txt";
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct Settings {
     #[serde(flatten)]
     map: BTreeMap<JrnSetting, String>,
+    // built on first use, as it is needed for every entry read
+    #[serde(skip)]
+    timestamp_format: OnceLock<TimestampFormat>,
+    // loaded on first use, only needed for encrypted entries
+    #[serde(skip)]
+    keys: OnceLock<Result<Keys, String>>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Hash, Eq, Ord, PartialOrd)]
@@ -21,6 +31,14 @@ pub enum JrnSetting {
     TagStart,
     TagDeliminator,
     Location,
+    TagCaseFold,
+    RecordUtcOffset,
+    DisplayTimeZone,
+    TimestampFormat,
+    Color,
+    Encrypt,
+    IdentityFile,
+    ObfuscateFileNames,
 }
 
 impl Default for Settings {
@@ -31,7 +49,18 @@ impl Default for Settings {
         map.insert(EditorArgs, String::from("+star"));
         map.insert(TagStart, String::from("-"));
         map.insert(TagDeliminator, String::from("_"));
-        Settings { map }
+        map.insert(TagCaseFold, String::from("false"));
+        map.insert(RecordUtcOffset, String::from("false"));
+        map.insert(DisplayTimeZone, String::from("original"));
+        map.insert(TimestampFormat, String::from("minutes"));
+        map.insert(Color, String::from("auto"));
+        map.insert(Encrypt, String::from("false"));
+        map.insert(ObfuscateFileNames, String::from("false"));
+        Settings {
+            map,
+            timestamp_format: OnceLock::new(),
+            keys: OnceLock::new(),
+        }
     }
 }
 
@@ -56,11 +85,22 @@ impl Settings {
     /// This function will not fail, but rather log warnings
     /// these can be used by the applications logger
     pub fn find_or_default() -> Self {
+        Settings::find_or_default_from(std::env::current_dir().ok())
+    }
+
+    /// find the configuration of the repo at root, see [Settings::find_or_default]
+    ///
+    /// root/.jrnconfig takes the place of the one in the current working dir
+    pub fn find_or_default_in(root: &Path) -> Self {
+        Settings::find_or_default_from(Some(root.to_path_buf()))
+    }
+
+    fn find_or_default_from(root: Option<PathBuf>) -> Self {
         let mut working_cfg: Settings = Settings::empty();
         let optional_paths: Vec<Option<PathBuf>> = vec![
             dirs::config_dir(),
             dirs::home_dir(),
-            std::env::current_dir().ok(),
+            root,
         ];
 
         // filter map possible config directories to config paths
@@ -103,6 +143,92 @@ impl Settings {
             .unwrap()
     }
 
+    /// true if tags should be lower cased when normalized
+    pub fn get_tag_case_fold(&self) -> bool {
+        self.get_bool(JrnSetting::TagCaseFold, false)
+    }
+
+    /// true if new entries should record the utc offset they were written in
+    pub fn get_record_utc_offset(&self) -> bool {
+        self.get_bool(JrnSetting::RecordUtcOffset, false)
+    }
+
+    /// The zone entry times are displayed in, either "original" or "local"
+    pub fn get_display_zone(&self) -> DisplayZone {
+        match self.map.get(&JrnSetting::DisplayTimeZone) {
+            Some(s) if s.trim().eq_ignore_ascii_case("local") => DisplayZone::Local,
+            _ => DisplayZone::Original,
+        }
+    }
+
+    /// When output is coloured, one of "auto", "always" or "never"
+    ///
+    /// Logs a warning and uses auto if the configured value is invalid
+    pub fn get_color(&self) -> ColorChoice {
+        match self.map.get(&JrnSetting::Color).map(|s| s.parse()) {
+            Some(Ok(choice)) => choice,
+            Some(Err(e)) => {
+                log::warn!("Invalid color setting, using auto\n{}", e);
+                ColorChoice::Auto
+            }
+            None => ColorChoice::Auto,
+        }
+    }
+
+    /// true if new entries should be encrypted, see [crate::crypt]
+    pub fn get_encrypt(&self) -> bool {
+        self.get_bool(JrnSetting::Encrypt, false)
+    }
+
+    /// true if encrypted entries should be given random file names
+    pub fn get_obfuscate_file_names(&self) -> bool {
+        self.get_bool(JrnSetting::ObfuscateFileNames, false)
+    }
+
+    /// The age identity used for encrypted entries, ~/.config/jrn/identity.txt unless set
+    pub fn get_identity_file(&self) -> PathBuf {
+        match self.map.get(&JrnSetting::IdentityFile) {
+            Some(path) if !path.trim().is_empty() => PathBuf::from(path.trim()),
+            _ => dirs::config_dir()
+                .unwrap_or_default()
+                .join("jrn")
+                .join(IDENTITY_FILE_NAME),
+        }
+    }
+
+    /// The keys read from the identity file, loaded once
+    pub(crate) fn keys(&self) -> Result<&Keys, JrnError> {
+        self.keys
+            .get_or_init(|| Keys::load(&self.get_identity_file()).map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(|e| JrnError::Encryption(e.clone()))
+    }
+
+    /// The format used to write and read entry timestamps
+    ///
+    /// Logs a warning and uses the default format if the configured format is invalid
+    pub fn get_timestamp_format(&self) -> &TimestampFormat {
+        self.timestamp_format.get_or_init(|| {
+            let configured = self.map.get(&JrnSetting::TimestampFormat);
+            match configured.map(|s| TimestampFormat::new(s)) {
+                Some(Ok(format)) => format,
+                Some(Err(e)) => {
+                    log::warn!("Invalid timestamp format, using default\n{}", e);
+                    TimestampFormat::default()
+                }
+                None => TimestampFormat::default(),
+            }
+        })
+    }
+
+    /// true if key is set to true, ignoring case, default if it is not set
+    fn get_bool(&self, key: JrnSetting, default: bool) -> bool {
+        self.map
+            .get(&key)
+            .map(|s| s.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or(default)
+    }
+
     fn get_editor_args(&self) -> Vec<&str> {
         self.map
             .get(&JrnSetting::EditorArgs).unwrap()
@@ -117,6 +243,11 @@ impl Settings {
             .map(|s| s.into())
     }
 
+    /// Every setting and its value, in the order settings are declared
+    pub fn iter(&self) -> impl Iterator<Item = (&JrnSetting, &String)> {
+        self.map.iter()
+    }
+
     /// Attempts to launch the editor based on the settings in this config
     pub fn launch_editor(&self, path: Option<&Path>) -> Result<(), JrnError> {
         let mut args: Vec<String> = Vec::new();
@@ -135,16 +266,17 @@ impl Settings {
         let editor = self.map.get(&JrnSetting::Editor).unwrap();
         log::info!("Launching editor \"{}\" with args {:?}", &editor, &args);
 
-        //TODO propagate err
-        let mut cmd = Command::new(&editor);
+        let mut cmd = Command::new(editor);
         cmd.args(args);
-        let mut child = cmd.spawn().unwrap();
-        child.wait().unwrap();
+        let mut child = cmd.spawn()?;
+        child.wait()?;
         Ok(())
     }
 
     pub fn set(&mut self, arg: JrnSetting, s: &str) {
         self.map.insert(arg, s.to_string());
+        self.timestamp_format = OnceLock::new();
+        self.keys = OnceLock::new();
     }
     
     // convenience method for an empty settings object
@@ -152,6 +284,8 @@ impl Settings {
     fn empty() -> Self {
         Settings {
             map: BTreeMap::new(),
+            timestamp_format: OnceLock::new(),
+            keys: OnceLock::new(),
         }
     }
 
@@ -161,6 +295,8 @@ impl Settings {
         for (setting, value) in other.map {
             self.map.entry(setting).or_insert(value);
         }
+        self.timestamp_format = OnceLock::new();
+        self.keys = OnceLock::new();
         self
     }
 
@@ -210,7 +346,7 @@ impl Settings {
             .open(path)?;
 
         self.serialize(&mut serializer)?;
-        file.write_all(&serializer.into_output_string().as_bytes())?;
+        file.write_all(serializer.into_output_string().as_bytes())?;
         Ok(())
     }
 }
@@ -219,6 +355,16 @@ impl Settings {
 mod test {
     use super::*;
 
+    #[test]
+    fn invalid_timestamp_format_uses_default() {
+        let mut settings = Settings::default();
+        settings.set(JrnSetting::TimestampFormat, "%Y/%m/%d");
+        assert_eq!(settings.get_timestamp_format(), &crate::TimestampFormat::default());
+        settings.set(JrnSetting::TimestampFormat, "seconds");
+        let seconds = crate::TimestampFormat::new("seconds").unwrap();
+        assert_eq!(settings.get_timestamp_format(), &seconds);
+    }
+
     #[test]
     fn write_default() {
         let settings = Settings::default();
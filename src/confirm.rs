@@ -0,0 +1,121 @@
+//! Asks before commands change entries
+//!
+//! The [Plan] of changes a command is about to make is shown and confirmed on a terminal.
+//! Without a terminal to ask on the command is refused unless confirmed up front,
+//! and a dry run only shows the plan
+//!     remove   2019-10-01_0930-work
+//!     Proceed? [y/N]
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use jrn::{JrnError, Plan};
+
+/// How a command that changes entries goes ahead
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Confirm {
+    /// ask on a terminal, refuse without one
+    #[default]
+    Ask,
+    /// go ahead without asking
+    Yes,
+    /// only show what would be changed
+    DryRun,
+}
+
+impl Confirm {
+    /// Goes ahead without asking unless ask is true, a dry run stays a dry run
+    pub fn asking_if(self, ask: bool) -> Self {
+        match self {
+            Confirm::Ask if !ask => Confirm::Yes,
+            other => other,
+        }
+    }
+
+    /// Shows plan and decides whether to carry it out
+    ///
+    /// An empty plan is never carried out, refusing to go ahead is an error
+    pub fn confirm(self, plan: &Plan) -> Result<bool, JrnError> {
+        let stdin = io::stdin();
+        let terminal = stdin.is_terminal();
+        self.decide(plan, terminal, &mut stdin.lock(), &mut io::stdout())
+    }
+
+    fn decide<R: BufRead, W: Write>(
+        self,
+        plan: &Plan,
+        terminal: bool,
+        input: &mut R,
+        out: &mut W,
+    ) -> Result<bool, JrnError> {
+        if plan.is_empty() {
+            return Ok(false);
+        }
+        for change in plan.changes() {
+            writeln!(out, "{}", change)?;
+        }
+
+        match self.asking_if(plan.asks()) {
+            Confirm::Yes => Ok(true),
+            Confirm::DryRun => Ok(false),
+            Confirm::Ask if !terminal => Err(JrnError::Unconfirmed(format!(
+                "{} needs --yes when not run from a terminal",
+                plan.command()
+            ))),
+            Confirm::Ask => {
+                write!(out, "Proceed? [y/N] ")?;
+                out.flush()?;
+                let mut answer = String::new();
+                input.read_line(&mut answer)?;
+                Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn plan() -> Plan {
+        let mut plan = Plan::new("rm");
+        plan.push(String::from("remove   2019-10-01_0930-work"));
+        plan
+    }
+
+    fn decide(
+        plan: &Plan,
+        confirm: Confirm,
+        terminal: bool,
+        input: &str,
+    ) -> (Result<bool, JrnError>, String) {
+        let mut out = Vec::new();
+        let decision = confirm.decide(plan, terminal, &mut input.as_bytes(), &mut out);
+        (decision, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn asks_on_a_terminal() {
+        let (decision, out) = decide(&plan(), Confirm::Ask, true, "y\n");
+        assert!(decision.unwrap());
+        assert_eq!(out, "remove   2019-10-01_0930-work\nProceed? [y/N] ");
+        assert!(!decide(&plan(), Confirm::Ask, true, "\n").0.unwrap());
+    }
+
+    #[test]
+    fn refuses_without_a_terminal() {
+        match decide(&plan(), Confirm::Ask, false, "y\n").0 {
+            Err(JrnError::Unconfirmed(_)) => (),
+            other => panic!("expected a refusal, found {:?}", other),
+        }
+        assert!(decide(&plan(), Confirm::Yes, false, "").0.unwrap());
+        assert!(decide(&plan(), Confirm::Ask.asking_if(false), false, "").0.unwrap());
+    }
+
+    #[test]
+    fn dry_runs_only_show_the_plan() {
+        let (decision, out) = decide(&plan(), Confirm::DryRun.asking_if(false), false, "");
+        assert!(!decision.unwrap());
+        assert_eq!(out, "remove   2019-10-01_0930-work\n");
+        assert!(!decide(&Plan::new("rm"), Confirm::Yes, false, "").0.unwrap());
+    }
+}
@@ -0,0 +1,232 @@
+//! Encryption of entries with age, see https://age-encryption.org
+//!
+//! Entries are encrypted to the x25519 identity found in the IdentityFile setting,
+//! which can be created with jrn keygen or age-keygen. An encrypted entry keeps its
+//! name with an .age extension added, so it can also be read with
+//!     age -d -i ~/.config/jrn/identity.txt 2019-10-01_0930-work.age
+//!
+//! With ObfuscateFileNames the name is replaced by a random one and kept
+//! as the first line of the encrypted text
+//!     jrn: 2019-10-01_0930-work
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use age::secrecy::ExposeSecret;
+use age::x25519;
+use tempfile::NamedTempFile;
+
+use crate::JrnError;
+
+/// the extension of encrypted entries
+pub(crate) static EXTENSION: &str = "age";
+static NAME_HEADER: &str = "jrn: ";
+/// editing happens in memory if the system has a ram backed tmpfs here
+static RAM_DIR: &str = "/dev/shm";
+
+/// The key entries are encrypted to and decrypted with
+pub(crate) struct Keys {
+    identity: x25519::Identity,
+}
+
+impl Keys {
+    /// Reads the first identity from an age identity file
+    pub fn load(path: &Path) -> Result<Self, JrnError> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            JrnError::Encryption(format!("can not read identity {}: {}", path.display(), e))
+        })?;
+        let identity = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .find_map(|line| line.parse::<x25519::Identity>().ok())
+            .ok_or_else(|| {
+                JrnError::Encryption(format!("no age identity found in {}", path.display()))
+            })?;
+        Ok(Keys { identity })
+    }
+
+    /// Writes a new identity file readable only by the current user, returning its public key
+    ///
+    /// An existing file is never replaced
+    pub fn generate(path: &Path) -> Result<String, JrnError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let identity = x25519::Identity::generate();
+        let public = identity.to_public().to_string();
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path)?;
+        writeln!(file, "# public key: {}", public)?;
+        writeln!(file, "{}", identity.to_string().expose_secret())?;
+        Ok(public)
+    }
+
+    pub fn public_key(&self) -> String {
+        self.identity.to_public().to_string()
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, JrnError> {
+        age::encrypt(&self.identity.to_public(), plaintext)
+            .map_err(|e| JrnError::Encryption(e.to_string()))
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, JrnError> {
+        age::decrypt(&self.identity, ciphertext).map_err(|e| JrnError::Encryption(e.to_string()))
+    }
+
+    /// Decrypts ciphertext into text
+    pub fn decrypt_text(&self, ciphertext: &[u8]) -> Result<String, JrnError> {
+        String::from_utf8(self.decrypt(ciphertext)?)
+            .map_err(|_| JrnError::Encryption(String::from("decrypted entry is not utf8")))
+    }
+}
+
+impl fmt::Debug for Keys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Keys({})", self.public_key())
+    }
+}
+
+impl PartialEq for Keys {
+    fn eq(&self, other: &Self) -> bool {
+        self.public_key() == other.public_key()
+    }
+}
+
+/// Prepends the name header to the body of an obfuscated entry
+pub(crate) fn with_name(name: &str, body: &str) -> String {
+    format!("{}{}\n{}", NAME_HEADER, name, body)
+}
+
+/// Splits decrypted text into the name from its header and the body
+pub(crate) fn split_name(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix(NAME_HEADER)?;
+    match rest.find('\n') {
+        Some(i) => Some((&rest[..i], &rest[i + 1..])),
+        None => Some((rest, "")),
+    }
+}
+
+/// A file name that says nothing about the entry
+pub(crate) fn random_name() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    let first = hasher.finish();
+    hasher.write_u64(first);
+    format!("{:016x}{:016x}.{}", first, hasher.finish(), EXTENSION)
+}
+
+/// A temporary plain text copy of an entry for the editor
+///
+/// The file is only readable by the current user, kept in memory where possible,
+/// and overwritten before it is removed
+pub(crate) struct Scratch {
+    file: NamedTempFile,
+}
+
+impl Scratch {
+    pub fn new(text: &str) -> Result<Self, JrnError> {
+        let dir = match Path::new(RAM_DIR).is_dir() {
+            true => PathBuf::from(RAM_DIR),
+            false => std::env::temp_dir(),
+        };
+        let mut file = tempfile::Builder::new().prefix("jrn-").suffix(".md").tempfile_in(dir)?;
+        file.write_all(text.as_bytes())?;
+        file.flush()?;
+        Ok(Scratch { file })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Reads the text back, after the editor has saved it
+    pub fn read(&self) -> Result<String, JrnError> {
+        // editors may replace the file rather than write to it, so open it again by path
+        let mut text = String::new();
+        File::open(self.path())?.read_to_string(&mut text)?;
+        Ok(text)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        if let Ok(mut file) = fs::OpenOptions::new().write(true).open(self.file.path()) {
+            let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+            let _ = file.seek(SeekFrom::Start(0));
+            let _ = file.write_all(&vec![0; len as usize]);
+            let _ = file.sync_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_with_generated_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys").join("identity.txt");
+        let public = Keys::generate(&path).unwrap();
+        assert!(Keys::generate(&path).is_err());
+
+        let keys = Keys::load(&path).unwrap();
+        assert_eq!(keys.public_key(), public);
+        let ciphertext = keys.encrypt(b"secret").unwrap();
+        assert!(!ciphertext.windows(6).any(|w| w == b"secret"));
+        assert_eq!(keys.decrypt_text(&ciphertext).unwrap(), "secret");
+    }
+
+    #[test]
+    fn other_keys_can_not_decrypt() {
+        let dir = tempfile::tempdir().unwrap();
+        Keys::generate(&dir.path().join("a")).unwrap();
+        Keys::generate(&dir.path().join("b")).unwrap();
+        let a = Keys::load(&dir.path().join("a")).unwrap();
+        let b = Keys::load(&dir.path().join("b")).unwrap();
+        assert!(b.decrypt(&a.encrypt(b"secret").unwrap()).is_err());
+    }
+
+    #[test]
+    fn splits_name_header() {
+        let text = with_name("2019-10-01_0930-work", "body\n");
+        assert_eq!(split_name(&text), Some(("2019-10-01_0930-work", "body\n")));
+        assert_eq!(split_name("no header"), None);
+    }
+
+    #[test]
+    fn random_names_differ() {
+        assert_ne!(random_name(), random_name());
+        assert!(random_name().ends_with(".age"));
+    }
+
+    #[test]
+    fn scratch_is_private() {
+        let scratch = Scratch::new("draft").unwrap();
+        assert_eq!(scratch.read().unwrap(), "draft");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(scratch.path()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o077, 0);
+        }
+        let path = scratch.path().to_path_buf();
+        drop(scratch);
+        assert!(!path.exists());
+    }
+}
@@ -11,7 +11,7 @@ pub trait IsDeliminator {
 
 impl IsDeliminator for char {
     fn is_deliminator(&self) -> bool {
-        DELIMINATORS.contains(&self)
+        DELIMINATORS.contains(self)
     }
 }
 
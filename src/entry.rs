@@ -1,14 +1,19 @@
-use lazy_static::lazy_static;
-use regex::Regex;
 use std::fmt::{self, Display, Formatter};
 use std::fs::{self, File};
-use std::io::{self, Read};
+use std::io::{self, BufRead, Read};
 use std::path::{Path, PathBuf};
 
-use super::{JrnError, Location, Settings, TimeStamp};
+use super::{tag, Location, Settings, TagAliases, Template, TimeStamp};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use crate::JrnRepo;
+use crate::{JrnError, JrnRepo};
+use crate::color::{paint, Style};
+use crate::crypt;
+
+/// the extension of the directory holding the attachments of an entry
+static ATTACHMENTS_EXTENSION: &str = "attachments";
+/// bytes read from the first line of an entry looking for its location
+static LOCATION_LINE_LIMIT: u64 = 1024;
 
 /// the in memory representation of a jrn entry
 #[derive(Debug, Eq, PartialOrd, PartialEq, Ord, Hash)]
@@ -27,70 +32,294 @@ impl JrnEntry {
         tags: Vec<String>,
         loc_arg: Option<String>,
     ) -> Self {
-        let creation_time = creation_time.unwrap_or_else(TimeStamp::now);
+        let creation_time = configured_time(creation_time.unwrap_or_else(TimeStamp::now), for_repo);
+        let tags = for_repo.tag_aliases().normalize_all(&tags);
 
-        // Pulls the location from the config if not given
-        let location = Location::configured_from(loc_arg, for_repo);
+        // Pulls the location from the config if not given, encrypted entries keep none
+        let location = match for_repo.get_encrypt() {
+            true => Location::default(),
+            false => Location::configured_from(loc_arg, for_repo),
+        };
         let mut entry = JrnEntry {
             creation_time,
             location,
             tags,
             file_path: PathBuf::new(),
         };
-        entry.build_file_path(for_repo);
+        let file_name = match (for_repo.get_encrypt(), for_repo.get_obfuscate_file_names()) {
+            (true, true) => crypt::random_name(),
+            (true, false) => format!("{}.{}", entry.file_name(for_repo), crypt::EXTENSION),
+            (false, _) => entry.file_name(for_repo),
+        };
+        entry.file_path = for_repo.root_path.join(file_name);
         entry
     }
 
     /// Reads an entry from a file path
-    pub fn read_entry(path: &Path, config: &Settings) -> Option<Self> {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(
-                r"(?x)
-            (?P<year>\d{4})
-            -
-            (?P<month>\d{2})
-            -
-            (?P<day>\d{2})
-            _
-            (?P<hr>\d{2})
-            (?P<min>\d{2})
-            -?
-            (?P<tags>.*)?
-            "
-            )
-            .unwrap();
+    ///
+    /// The time and tags are parsed from the file name, tags are decoded with [tag::decode]
+    /// and normalized, so may differ from those in the file name.
+    /// The location is read from the first line of plain entries, see [Location::split_header]
+    ///
+    /// Encrypted entries drop their .age extension, obfuscated entries are
+    /// decrypted to read their name, see [crypt]. Neither keeps a location
+    pub fn read_entry(path: &Path, config: &Settings, aliases: &TagAliases) -> Option<Self> {
+        if let Some(mut entry) = JrnEntry::read_name(path, config, aliases) {
+            if !entry.is_encrypted() {
+                entry.location = read_location(path);
+            }
+            return Some(entry);
+        }
+        if !is_encrypted(path) {
+            return None;
+        }
+        let (creation_time, tags) =
+            parse_file_name(&obfuscated_name(path, config)?, config, aliases)?;
+        Some(JrnEntry {
+            creation_time,
+            location: Location::default(),
+            tags,
+            file_path: PathBuf::from(path),
+        })
+    }
+
+    /// Reads an entry from its file name alone, see [JrnEntry::read_entry]
+    ///
+    /// Nothing is read from the file, so the location is left out
+    /// and obfuscated entries are skipped rather than decrypted
+    pub(crate) fn read_name(path: &Path, config: &Settings, aliases: &TagAliases) -> Option<Self> {
+        let (creation_time, tags) = parse_file_name(stored_name(path)?, config, aliases)?;
+        Some(JrnEntry {
+            creation_time,
+            location: Location::default(),
+            tags,
+            file_path: PathBuf::from(path),
+        })
+    }
+
+    /// Pushes the normalized tag to this entry, unless it is already present
+    ///
+    /// On failure to rename the file the entry is left unchanged
+    pub fn push_tag(
+        &mut self,
+        tag: &str,
+        config: &Settings,
+        aliases: &TagAliases,
+    ) -> Result<(), JrnError> {
+        let tag = aliases.normalize(tag);
+        if tag.is_empty() || self.tags.contains(&tag) {
+            return Ok(());
+        }
+        self.tags.push(tag);
+        if let Err(e) = self.update_file_path(config) {
+            self.tags.pop();
+            self.build_file_path(config);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Removes tag from this entry, renaming its file
+    ///
+    /// On failure to rename the file the entry is left unchanged
+    pub fn remove_tag(
+        &mut self,
+        tag: &str,
+        config: &Settings,
+        aliases: &TagAliases,
+    ) -> Result<(), JrnError> {
+        let tag = aliases.normalize(tag);
+        let index = match self.tags.iter().position(|t| *t == tag) {
+            Some(index) => index,
+            None => return Ok(()),
         };
+        let removed = self.tags.remove(index);
+        if let Err(e) = self.update_file_path(config) {
+            self.tags.insert(index, removed);
+            self.build_file_path(config);
+            return Err(e);
+        }
+        Ok(())
+    }
 
-        if let Some(file_path) = path.to_str() {
-            if let Some(captures) = RE.captures(file_path) {
-                let year: i32 = captures.name("year").unwrap().as_str().parse().unwrap();
-                let month: u32 = captures.name("month").unwrap().as_str().parse().unwrap();
-                let day: u32 = captures.name("day").unwrap().as_str().parse().unwrap();
-                let hr: u32 = captures.name("hr").unwrap().as_str().parse().unwrap();
-                let min: u32 = captures.name("min").unwrap().as_str().parse().unwrap();
-                let tag_str: &str = captures.name("tags").unwrap().as_str();
-
-                let creation_time = TimeStamp::from_ymdhm(year, month, day, hr, min);
-                let tag_delim = config.get_tag_deliminator();
-                let tags: Vec<String> = tag_str.split(tag_delim).map(String::from).collect();
-                let file_path: PathBuf = PathBuf::from(path);
-                let entry = JrnEntry {
-                    creation_time,
-                    location: Location::default(), //TODO get location from file_path
-                    tags,
-                    file_path,
-                };
-                return Some(entry);
-            }
+    /// Replaces the tags of this entry, renaming its file
+    ///
+    /// On failure to rename the file the entry is left unchanged
+    pub fn set_tags(
+        &mut self,
+        tags: &[String],
+        config: &Settings,
+        aliases: &TagAliases,
+    ) -> Result<(), JrnError> {
+        let old = std::mem::replace(&mut self.tags, aliases.normalize_all(tags));
+        if let Err(e) = self.update_file_path(config) {
+            self.tags = old;
+            self.build_file_path(config);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// The tags in the file name of this entry, those written before tags were encoded
+    /// read verbatim, see [tag::is_legacy]
+    ///
+    /// Returns None if they are the tags it already has.
+    /// Encrypted entries were never named with unencoded tags
+    pub(crate) fn legacy_tags(
+        &self,
+        config: &Settings,
+        aliases: &TagAliases,
+    ) -> Option<Vec<String>> {
+        if self.is_encrypted() {
+            return None;
+        }
+        let file_name = self.file_path.file_name()?.to_str()?;
+        let (_, tag_str) = split_file_name(file_name, config)?;
+        let delim = config.get_tag_deliminator();
+        let tags: Vec<String> = tag_str
+            .split(delim)
+            .map(|t| match tag::is_legacy(t, delim) {
+                true => String::from(t),
+                false => tag::decode(t),
+            })
+            .collect();
+        let tags = aliases.normalize_all(&tags);
+        match tags != self.tags {
+            true => Some(tags),
+            false => None,
+        }
+    }
+
+    /// Returns true if any tag of this entry satisfies query, see [tag::matches]
+    pub fn has_tag(&self, query: &str) -> bool {
+        self.tags.iter().any(|t| tag::matches(t, query))
+    }
+
+    /// Moves an entry not yet written to creation_time, see [JrnEntry::retime] for written ones
+    pub(crate) fn set_time(&mut self, creation_time: TimeStamp, config: &Settings) {
+        self.creation_time = configured_time(creation_time, config);
+        self.build_file_path(config);
+    }
+
+    /// Moves this entry to creation_time, renaming its file
+    ///
+    /// On failure to rename the file the entry is left unchanged
+    /// Attachments move along, links to them in the body are rewritten
+    pub fn retime(&mut self, creation_time: TimeStamp, config: &Settings) -> Result<(), JrnError> {
+        let old = self.creation_time;
+        let old_attachments = self.attachment_dir(config);
+        self.creation_time = configured_time(creation_time, config);
+        if let Err(e) = self.update_file_path(config) {
+            self.creation_time = old;
+            self.build_file_path(config);
+            return Err(e);
+        }
+        if let Err(e) = self.move_attachments(&old_attachments, config) {
+            log::warn!(
+                "Attachments of {} were left in {}: {}",
+                self.file_path.display(),
+                old_attachments.display(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    /// Gives this entry the time and tags of name, renaming its file to name
+    ///
+    /// The file is named exactly name, even if its tags are not in canonical form,
+    /// so renames by [JrnEntry::rewrite_file_path] can be undone.
+    /// On failure to rename the file the entry is left unchanged
+    /// Attachments move along as in [JrnEntry::retime]
+    pub fn rename(
+        &mut self,
+        name: &str,
+        config: &Settings,
+        aliases: &TagAliases,
+    ) -> Result<(), JrnError> {
+        let (creation_time, tags) = parse_file_name(name, config, aliases)
+            .ok_or_else(|| JrnError::InvalidTime(String::from(name)))?;
+        let old_tags = std::mem::replace(&mut self.tags, tags);
+        if let Err(e) = self.retime(creation_time, config) {
+            self.tags = old_tags;
+            self.build_file_path(config);
+            return Err(e);
+        }
+        if self.is_obfuscated(config) || stored_name(&self.file_path) == Some(name) {
+            return Ok(());
         }
+        let file_name = match self.is_encrypted() {
+            true => format!("{}.{}", name, crypt::EXTENSION),
+            false => String::from(name),
+        };
+        let path = self.file_path.with_file_name(file_name);
+        match rename_no_clobber(&self.file_path, &path) {
+            Ok(()) => self.file_path = path,
+            Err(e) => log::warn!("{} was not renamed to {}: {}", self.file_path.display(), name, e),
+        }
+        Ok(())
+    }
 
-        None
+    /// The directory next to the entry file holding its attachments
+    ///
+    /// It is named after the time of the entry, so it is kept when tags change,
+    /// or after the random file name of obfuscated entries
+    pub fn attachment_dir(&self, config: &Settings) -> PathBuf {
+        let stem = match self.is_obfuscated(config) {
+            true => self.file_path.file_stem().unwrap_or_default().to_string_lossy().into_owned(),
+            false => config.get_timestamp_format().format(&self.creation_time),
+        };
+        self.file_path.with_file_name(format!("{}.{}", stem, ATTACHMENTS_EXTENSION))
+    }
+
+    /// Copies file into the attachment directory and links it at the end of the body
+    ///
+    /// Images are embedded, other files linked. A file of the same name is never replaced,
+    /// the copy is numbered instead. Returns the path of the copy
+    pub fn attach(&self, file: &Path, config: &Settings) -> Result<PathBuf, JrnError> {
+        let name = file
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| {
+                let e = io::Error::new(io::ErrorKind::InvalidInput, "attachments need a file name");
+                JrnError::IO(e)
+            })?;
+        let dir = self.attachment_dir(config);
+        fs::create_dir_all(&dir)?;
+        let target = free_path(&dir.join(name));
+        fs::copy(file, &target)?;
+
+        let dir_name = dir.file_name().unwrap_or_default().to_string_lossy();
+        let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+        let mut body = match self.file_path.exists() {
+            true => self.body(config)?,
+            false => String::new(),
+        };
+        if !body.is_empty() && !body.ends_with('\n') {
+            body.push('\n');
+        }
+        body.push_str(&markdown_link(&file_name, &format!("{}/{}", dir_name, file_name)));
+        body.push('\n');
+        if let Err(e) = self.write_body(config, &body) {
+            let _ = fs::remove_file(&target);
+            return Err(e);
+        }
+        Ok(target)
     }
 
-    /// Pushes a tag to this entry
-    pub fn push_tag(&mut self, tag: &str, config: &Settings) -> std::io::Result<()> {
-        self.tags.push(String::from(tag));
-        self.update_file_path(config)?;
+    /// Moves the attachments from old to the directory of this entry, rewriting links to them
+    fn move_attachments(&self, old: &Path, config: &Settings) -> Result<(), JrnError> {
+        let new = self.attachment_dir(config);
+        if old == new || !old.exists() {
+            return Ok(());
+        }
+        rename_no_clobber(old, &new)?;
+        let old_link = format!("{}/", old.file_name().unwrap_or_default().to_string_lossy());
+        let new_link = format!("{}/", new.file_name().unwrap_or_default().to_string_lossy());
+        let body = self.body(config)?;
+        if body.contains(&old_link) {
+            self.write_body(config, &body.replace(&old_link, &new_link))?;
+        }
         Ok(())
     }
 
@@ -106,65 +335,391 @@ impl JrnEntry {
         hasher.finish()
     }
 
-    pub fn delete(&self) -> io::Result<()> {
-        fs::remove_file(&self.file_path)?;
+    /// The id used to refer to this entry from the command line
+    pub fn id(&self) -> String {
+        format!("{:x}", self.get_hash())
+    }
+
+    /// The name this entry is stored under, which differs from [JrnEntry::file_name]
+    /// until its file is renamed to match its tags, see [JrnEntry::rewrite_file_path]
+    pub(crate) fn stored_name(&self, config: &Settings) -> String {
+        match self.is_obfuscated(config) {
+            true => self.file_name(config),
+            false => stored_name(&self.file_path)
+                .map_or_else(|| self.file_name(config), String::from),
+        }
+    }
+
+    /// true if the file of this entry is encrypted, see [crypt]
+    pub fn is_encrypted(&self) -> bool {
+        is_encrypted(&self.file_path)
+    }
+
+    /// true if the file name of this encrypted entry is random,
+    /// its name is kept in the file instead
+    pub fn is_obfuscated(&self, config: &Settings) -> bool {
+        self.is_encrypted()
+            && self
+                .file_path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_none_or(|stem| config.get_timestamp_format().parse_prefix(stem).is_none())
+    }
+
+    /// Reads the text of this entry from its file, decrypting it if needed
+    pub fn body(&self, config: &Settings) -> Result<String, JrnError> {
+        if !self.is_encrypted() {
+            let mut contents = String::new();
+            File::open(&self.file_path)?.read_to_string(&mut contents)?;
+            return Ok(String::from(Location::split_header(&contents).1));
+        }
+
+        let text = config.keys()?.decrypt_text(&fs::read(&self.file_path)?)?;
+        match self.is_obfuscated(config) {
+            true => {
+                let body = crypt::split_name(&text).map_or(text.as_str(), |(_, body)| body);
+                Ok(String::from(body))
+            }
+            false => Ok(text),
+        }
+    }
+
+    /// Reads the text of this entry, or describes why it could not be read
+    pub fn body_or_error(&self, config: &Settings) -> String {
+        self.body(config)
+            .unwrap_or_else(|e| format!("[cannot read {}: {}]", self.file_path.display(), e))
+    }
+
+    /// Replaces the text of this entry, encrypting it if needed
+    pub fn write_body(&self, config: &Settings, text: &str) -> Result<(), JrnError> {
+        if !self.is_encrypted() {
+            fs::write(&self.file_path, self.location.with_header(text))?;
+            return Ok(());
+        }
+
+        let ciphertext = match self.is_obfuscated(config) {
+            true => {
+                let named = crypt::with_name(&self.file_name(config), text);
+                config.keys()?.encrypt(named.as_bytes())?
+            }
+            false => config.keys()?.encrypt(text.as_bytes())?,
+        };
+        fs::write(&self.file_path, ciphertext)?;
+        Ok(())
+    }
+
+    /// Moves the entry file together with its attachments into dir, keeping their names
+    pub fn move_into(&self, dir: &Path, config: &Settings) -> io::Result<()> {
+        let attachments = self.attachment_dir(config);
+        let file_name = self.file_path.file_name().unwrap_or_default();
+        rename_no_clobber(&self.file_path, &dir.join(file_name))?;
+        if attachments.is_dir() {
+            let name = attachments.file_name().unwrap_or_default();
+            rename_no_clobber(&attachments, &dir.join(name))?;
+        }
         Ok(())
     }
 
+    /// Builds the file name from the metadata of this entry
+    /// keeping the entry in its current directory
+    ///
+    /// Obfuscated entries keep their random file name
     fn build_file_path(&mut self, config: &Settings) {
-        let mut file_name = String::new();
-        let tag_start = config.get_tag_start();
-        let tag_delim = config.get_tag_deliminator();
-
-        //handle time
-        file_name.push_str(&self.creation_time.to_string());
-
-        //handle tags
-        if !self.tags.is_empty() {
-            file_name.push(tag_start);
-        }
-        let tag_len = self.tags.len();
-        for (i, tag) in self.tags.iter().enumerate() {
-            file_name.push_str(tag);
-            if i < (tag_len - 1) {
-                file_name.push(tag_delim);
-            }
+        self.file_path = self.built_file_path(config);
+    }
+
+    /// The path of the file built from the metadata of this entry,
+    /// see [JrnEntry::rewrite_file_path]
+    pub(crate) fn built_file_path(&self, config: &Settings) -> PathBuf {
+        if self.is_obfuscated(config) {
+            return self.file_path.clone();
+        }
+        let mut file_name = self.file_name(config);
+        if self.is_encrypted() {
+            file_name.push('.');
+            file_name.push_str(crypt::EXTENSION);
+        }
+        self.file_path.with_file_name(file_name)
+    }
+
+    /// The name of this entry, which is its file name unless encrypted
+    pub fn file_name(&self, config: &Settings) -> String {
+        self.file_name_with_tags(&self.tags, config)
+    }
+
+    /// The name this entry would have with tags instead of its own
+    pub(crate) fn file_name_with_tags(&self, tags: &[String], config: &Settings) -> String {
+        name_of(&self.creation_time, tags, config)
+    }
+
+    /// The name this entry would have once moved to creation_time, see [JrnEntry::retime]
+    pub(crate) fn file_name_at(&self, creation_time: TimeStamp, config: &Settings) -> String {
+        name_of(&configured_time(creation_time, config), &self.tags, config)
+    }
+
+    /// Renames the file if its name no longer matches this entries metadata
+    ///
+    /// Returns true if the file was renamed, obfuscated entries are never renamed
+    pub fn rewrite_file_path(&mut self, config: &Settings) -> Result<bool, JrnError> {
+        let old = self.file_path.clone();
+        self.build_file_path(config);
+        if old == self.file_path {
+            return Ok(false);
         }
-        let path = PathBuf::from(file_name);
-        self.file_path = path;
+        if let Err(e) = rename_no_clobber(&old, &self.file_path) {
+            self.file_path = old;
+            return Err(e.into());
+        }
+        Ok(true)
     }
 
-    fn update_file_path(&mut self, config: &Settings) -> std::io::Result<()> {
+    /// Moves the file to the name built from this entries metadata
+    ///
+    /// Obfuscated entries keep their file, the name inside it is rewritten
+    fn update_file_path(&mut self, config: &Settings) -> Result<(), JrnError> {
+        if self.is_obfuscated(config) {
+            let body = self.body(config)?;
+            return self.write_body(config, &body);
+        }
         let old = self.file_path.clone();
         self.build_file_path(config);
-        std::fs::rename(old, &self.file_path)?;
+        rename_no_clobber(&old, &self.file_path)?;
         Ok(())
     }
 }
 
+/// Parses the time and normalized tags from the name of an entry
+fn parse_file_name(
+    file_name: &str,
+    config: &Settings,
+    aliases: &TagAliases,
+) -> Option<(TimeStamp, Vec<String>)> {
+    let (creation_time, tag_str) = split_file_name(file_name, config)?;
+    let tag_delim = config.get_tag_deliminator();
+    let tags: Vec<String> = tag_str
+        .split(tag_delim)
+        .map(tag::decode)
+        .collect();
+    Some((creation_time, aliases.normalize_all(&tags)))
+}
+
+/// true if path is the attachment directory of an entry, see [JrnEntry::attachment_dir]
+pub(crate) fn is_attachment_dir(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some(ATTACHMENTS_EXTENSION) && path.is_dir()
+}
+
+/// path, or the first of path-1, path-2... that does not exist yet
+pub(crate) fn free_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut candidate = path.to_path_buf();
+    let mut n = 1;
+    while candidate.exists() {
+        candidate.set_file_name(format!("{}-{}{}", stem, n, extension));
+        n += 1;
+    }
+    candidate
+}
+
+/// A Markdown link to target named name, embedded if it is an image
+fn markdown_link(name: &str, target: &str) -> String {
+    static IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp"];
+    let is_image = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+    // spaces and parentheses would end the link early
+    let target = match target.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        true => format!("<{}>", target),
+        false => String::from(target),
+    };
+    let name = name.replace('[', "\\[").replace(']', "\\]");
+    match is_image {
+        true => format!("![{}]({})", name, target),
+        false => format!("[{}]({})", name, target),
+    }
+}
+
+fn is_encrypted(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some(crypt::EXTENSION)
+}
+
+/// The name of the entry file at path, without the extension of encrypted entries
+pub(crate) fn stored_name(path: &Path) -> Option<&str> {
+    let file_name = path.file_name().and_then(|name| name.to_str())?;
+    match is_encrypted(path) {
+        true => file_name.strip_suffix(crypt::EXTENSION)?.strip_suffix('.'),
+        false => Some(file_name),
+    }
+}
+
+/// Decrypts the name kept in an obfuscated entry
+///
+/// Files that are not entries or can not be decrypted are skipped,
+/// with a warning only if there are keys to decrypt them with
+fn obfuscated_name(path: &Path, config: &Settings) -> Option<String> {
+    let keys = config.keys().ok()?;
+    let text = fs::read(path)
+        .map_err(JrnError::from)
+        .and_then(|ciphertext| keys.decrypt_text(&ciphertext));
+    match text {
+        Ok(text) => crypt::split_name(&text).map(|(name, _)| String::from(name)),
+        Err(e) => {
+            log::warn!("Skipping {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// The name of an entry written at creation_time with tags
+fn name_of(creation_time: &TimeStamp, tags: &[String], config: &Settings) -> String {
+    let mut file_name = String::new();
+    let tag_start = config.get_tag_start();
+    let tag_delim = config.get_tag_deliminator();
+
+    //handle time
+    file_name.push_str(&config.get_timestamp_format().format(creation_time));
+
+    //handle tags
+    if !tags.is_empty() {
+        file_name.push(tag_start);
+    }
+    let tag_len = tags.len();
+    for (i, t) in tags.iter().enumerate() {
+        file_name.push_str(&tag::encode(t, tag_delim));
+        if i < (tag_len - 1) {
+            file_name.push(tag_delim);
+        }
+    }
+    file_name
+}
+
+/// Applies the settings for recording times to t
+fn configured_time(t: TimeStamp, config: &Settings) -> TimeStamp {
+    let t = if config.get_record_utc_offset() {
+        t
+    } else {
+        t.without_offset()
+    };
+    config.get_timestamp_format().truncate(t)
+}
+
+/// Reads the location from the first line of the plain entry at path, see [Location::split_header]
+fn read_location(path: &Path) -> Location {
+    let mut line = String::new();
+    let read = File::open(path)
+        .and_then(|file| io::BufReader::new(file.take(LOCATION_LINE_LIMIT)).read_line(&mut line));
+    match read {
+        Ok(_) => Location::split_header(&line).0,
+        Err(_) => Location::default(),
+    }
+}
+
+/// Renames from to to, refusing to replace another entry
+pub(crate) fn rename_no_clobber(from: &Path, to: &Path) -> io::Result<()> {
+    if from != to && to.exists() {
+        let msg = format!("{} already exists", to.display());
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, msg));
+    }
+    fs::rename(from, to)
+}
+
+/// Splits the name of an entry into its time and its encoded tags
+fn split_file_name<'a>(file_name: &'a str, config: &Settings) -> Option<(TimeStamp, &'a str)> {
+    let (creation_time, rest) = config.get_timestamp_format().parse_prefix(file_name)?;
+    Some((creation_time, rest.strip_prefix(config.get_tag_start()).unwrap_or(rest)))
+}
+
 static DISPLAY_LENGTH: usize = 100;
 
+/// Displays an entry with its timestamp converted to the configured [DisplayZone]
+///
+/// Both the metadata and the body are shown unless disabled,
+/// or the entry is written following a [Template]. Encrypted bodies are decrypted
+pub struct EntryDisplay<'a> {
+    entry: &'a JrnEntry,
+    config: &'a Settings,
+    metadata: bool,
+    body: bool,
+    template: Option<&'a Template>,
+    color: bool,
+}
+
+impl JrnEntry {
+    pub fn display<'a>(&'a self, config: &'a Settings) -> EntryDisplay<'a> {
+        EntryDisplay {
+            entry: self,
+            config,
+            metadata: true,
+            body: true,
+            template: None,
+            color: false,
+        }
+    }
+}
+
+impl<'a> EntryDisplay<'a> {
+    /// Sets whether the id, time, location and tags are shown
+    pub fn metadata(mut self, show: bool) -> Self {
+        self.metadata = show;
+        self
+    }
+
+    /// Sets whether the text of the entry is shown
+    pub fn body(mut self, show: bool) -> Self {
+        self.body = show;
+        self
+    }
+
+    /// Sets whether the metadata is ANSI coloured
+    pub fn color(mut self, enabled: bool) -> Self {
+        self.color = enabled;
+        self
+    }
+
+    /// Writes the entry following template instead
+    pub fn template(mut self, template: Option<&'a Template>) -> Self {
+        self.template = template;
+        self
+    }
+}
+
 impl Display for JrnEntry {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        use std::iter::{repeat, FromIterator};
-        let separator = String::from_iter(repeat('-').take(DISPLAY_LENGTH));
+        self.display(&Settings::default()).fmt(f)
+    }
+}
+
+impl Display for EntryDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(template) = self.template {
+            return template.write(self.entry, self.config, self.color, f);
+        }
+
+        if self.metadata {
+            let color = self.color;
+            let separator = paint("-".repeat(DISPLAY_LENGTH), Style::Header, color);
 
-        writeln!(f, "{}", &separator)?;
-        writeln!(f, "entry     {:x}", self.get_hash())?;
-        writeln!(f, "time      {}", self.creation_time)?;
-        writeln!(f, "location  {}", self.location)?;
-        write!(f, "tags      ")?;
-        for tag in &self.tags {
-            write!(f, "{} ", tag)?;
+            writeln!(f, "{}", &separator)?;
+            writeln!(f, "entry     {}", paint(self.entry.id(), Style::Id, color))?;
+            let time = self.entry.creation_time.in_zone(self.config.get_display_zone());
+            writeln!(f, "time      {}", paint(time, Style::Time, color))?;
+            writeln!(f, "location  {}", paint(&self.entry.location, Style::Location, color))?;
+            write!(f, "tags      ")?;
+            for tag in &self.entry.tags {
+                write!(f, "{} ", paint(tag, Style::Tag, color))?;
+            }
+            writeln!(f)?;
+            writeln!(f, "{}", &separator)?;
         }
-        writeln!(f)?;
-        writeln!(f, "{}", &separator)?;
 
         //write the contents of the file
-        let mut file = File::open(&self.file_path).expect("File Not Found");
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).unwrap();
-        writeln!(f, "{}", contents)?;
+        if self.body {
+            writeln!(f, "{}", self.entry.body_or_error(self.config))?;
+        }
         Ok(())
     }
 }
@@ -174,14 +729,73 @@ mod test {
     use super::*;
 
     #[test]
-    fn error_on_remove_nonexistent_entry() {
+    fn error_on_moving_nonexistent_entry() {
         let entry = JrnEntry {
             creation_time: TimeStamp::now(),
             location: Location::default(),
             tags: vec!(),
             file_path: PathBuf::from("/test"),
         };
-        entry.delete().expect_err("Found nonexistent entry");
+        entry
+            .move_into(Path::new("/trash"), &Settings::default())
+            .expect_err("Found nonexistent entry");
+    }
+
+    #[test]
+    fn read_entry_normalizes_tags() {
+        let config = Settings::default();
+        let mut aliases = TagAliases::default();
+        aliases.insert("mtg", "meeting");
+        let path = PathBuf::from("2019-10-01_0930-mtg_meeting_work");
+        let entry = JrnEntry::read_entry(&path, &config, &aliases).unwrap();
+        assert_eq!(entry.tags, vec!["meeting", "work"]);
+        assert_eq!(entry.file_path, path);
+    }
+
+    #[test]
+    fn nested_and_namespaced_tags_round_trip() {
+        let config = Settings::default();
+        let mut entry = JrnEntry {
+            creation_time: TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap(),
+            location: Location::default(),
+            tags: vec![
+                String::from("work/projectx/design"),
+                String::from("person:alice"),
+                String::from("to_do"),
+            ],
+            file_path: PathBuf::from("journal/old_name"),
+        };
+        entry.build_file_path(&config);
+        assert_eq!(
+            entry.file_path,
+            PathBuf::from("journal/2019-10-01_0930-work+projectx+design_person=alice_to%5Fdo")
+        );
+
+        let read = JrnEntry::read_entry(&entry.file_path, &config, &TagAliases::default()).unwrap();
+        assert_eq!(read.tags, entry.tags);
+        assert_eq!(read.creation_time, entry.creation_time);
     }
 
+    #[test]
+    fn reads_legacy_tags_verbatim() {
+        let (config, aliases) = (Settings::default(), TagAliases::default());
+        let path = Path::new("journal/2019-10-01_0930-c++_work+projectx_a:b");
+        let entry = JrnEntry::read_entry(path, &config, &aliases).unwrap();
+        assert_eq!(entry.tags, vec!["c//", "work/projectx", "a:b"]);
+        let legacy = entry.legacy_tags(&config, &aliases).unwrap();
+        assert_eq!(legacy, vec!["c++", "work/projectx", "a:b"]);
+
+        let path = Path::new("journal/2019-10-01_0930-c%2B%2B_work+projectx");
+        let entry = JrnEntry::read_entry(path, &config, &aliases).unwrap();
+        assert_eq!(entry.legacy_tags(&config, &aliases), None);
+    }
+
+    #[test]
+    fn links_attachments() {
+        assert_eq!(markdown_link("a.PNG", "d/a.PNG"), "![a.PNG](d/a.PNG)");
+        assert_eq!(
+            markdown_link("report [final].pdf", "d/report [final].pdf"),
+            "[report \\[final\\].pdf](<d/report [final].pdf>)"
+        );
+    }
 }
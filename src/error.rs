@@ -7,6 +7,15 @@ pub enum JrnError {
     Serialization(BoxedError),
     InvalidRegex,
     ParseIntError(BoxedError),
+    InvalidTimestampFormat(String),
+    InvalidTime(String),
+    EntryNotFound(String),
+    AmbiguousEntry(String),
+    InvalidColorChoice(String),
+    InvalidImport(String),
+    Encryption(String),
+    /// a command that needs confirmation could not ask for it
+    Unconfirmed(String),
 }
 
 impl std::error::Error for JrnError {}
@@ -35,6 +44,12 @@ impl From<ron::de::Error> for JrnError {
     }
 }
 
+impl From<serde_json::Error> for JrnError {
+    fn from(err: serde_json::Error) -> Self {
+        JrnError::Serialization(Box::new(err))
+    }
+}
+
 impl From<regex::Error> for JrnError {
     fn from(_err: regex::Error) -> Self {
         JrnError::InvalidRegex
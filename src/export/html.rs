@@ -0,0 +1,306 @@
+//! Renders the journal as a static html site
+//!
+//! The site needs nothing but a browser, all styling is inlined and no assets are fetched.
+//! It is laid out as
+//!     index.html              every entry, newest first
+//!     entries/<id>.html       the permalink of each entry, its body rendered from Markdown
+//!     tags.html               every tag and how often it is used
+//!     tags/<tag>.html         the entries carrying a tag
+//!     archive/<yyyy-mm>.html  the entries written in a month
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use chrono::Datelike;
+use pulldown_cmark::{html, Options, Parser};
+
+use crate::{DisplayZone, JrnEntry, JrnError, Settings, TagContainer};
+
+static STYLE: &str = "\
+body { font-family: sans-serif; max-width: 50em; margin: 2em auto; padding: 0 1em; color: #222; }
+nav { border-bottom: 1px solid #ccc; padding-bottom: .5em; margin-bottom: 1em; }
+nav a { margin-right: 1em; }
+.meta { color: #666; font-size: .9em; }
+.tag { background: #eef; border-radius: 3px; padding: 0 .3em; margin-right: .3em;
+       text-decoration: none; }
+ul.entries { list-style: none; padding: 0; }
+ul.entries li { margin: .4em 0; }
+pre { background: #f6f6f6; padding: .5em; overflow-x: auto; }";
+
+/// Writes the site for entries into out_dir, entries are expected in chronological order
+pub(crate) fn write_site(
+    out_dir: &Path,
+    entries: &[JrnEntry],
+    tags: &TagContainer,
+    config: &Settings,
+) -> Result<(), JrnError> {
+    for dir in &["entries", "tags", "archive"] {
+        fs::create_dir_all(out_dir.join(dir))?;
+    }
+    let mut site = Site {
+        entries,
+        tags,
+        config,
+        zone: config.get_display_zone(),
+        months: BTreeMap::new(),
+    };
+    for entry in entries.iter().rev() {
+        site.months.entry(site.month(entry)).or_default().push(entry);
+    }
+
+    for entry in entries {
+        let body = entry.body(config)?;
+        let page = site.entry_page(entry, &body);
+        fs::write(out_dir.join("entries").join(format!("{}.html", entry.id())), page)?;
+        copy_attachments(entry, config, &out_dir.join("entries"))?;
+    }
+
+    let newest_first: Vec<&JrnEntry> = entries.iter().rev().collect();
+    fs::write(out_dir.join("index.html"), site.list_page("Journal", "", &newest_first))?;
+
+    let by_path: HashMap<&Path, &JrnEntry> =
+        entries.iter().map(|e| (e.file_path.as_path(), e)).collect();
+    let sorted_tags = tags.sorted();
+    for tag in &sorted_tags {
+        let mut tagged: Vec<&JrnEntry> = tags
+            .entries(&tag.1)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|path| by_path.get(path.as_path()).copied())
+            .collect();
+        tagged.sort_by(|a, b| b.cmp(a));
+        let title = format!("Tagged {}", tag.1);
+        let page = site.list_page(&title, "../", &tagged);
+        fs::write(out_dir.join("tags").join(tag_page(&tag.1)), page)?;
+    }
+
+    let mut content = String::from("<h1>Tags</h1>\n<ul class=\"entries\">\n");
+    for tag in &sorted_tags {
+        let _ = writeln!(content, "<li>{} {}</li>", site.tag_link(&tag.1, ""), tag.0);
+    }
+    content.push_str("</ul>\n");
+    fs::write(out_dir.join("tags.html"), page("Tags", "", &site.nav(""), &content))?;
+
+    for (month, month_entries) in &site.months {
+        let title = format!("Written in {}", month);
+        let page = site.list_page(&title, "../", month_entries);
+        fs::write(out_dir.join("archive").join(format!("{}.html", month)), page)?;
+    }
+    Ok(())
+}
+
+struct Site<'a> {
+    entries: &'a [JrnEntry],
+    tags: &'a TagContainer,
+    config: &'a Settings,
+    zone: DisplayZone,
+    /// the entries of each month, newest first
+    months: BTreeMap<String, Vec<&'a JrnEntry>>,
+}
+
+impl Site<'_> {
+    /// The archive an entry belongs to, 2019-10
+    fn month(&self, entry: &JrnEntry) -> String {
+        let date = entry.creation_time.in_zone(self.zone).date();
+        format!("{:04}-{:02}", date.year(), date.month())
+    }
+
+    fn entry_page(&self, entry: &JrnEntry, body: &str) -> String {
+        let time = entry.creation_time.in_zone(self.zone).to_string();
+        let mut content = format!("<article>\n<h1>{}</h1>\n", escape(&time));
+        let _ = writeln!(content, "<p class=\"meta\">{}</p>", self.meta(entry, "../"));
+
+        let mut rendered = String::new();
+        html::push_html(&mut rendered, Parser::new_ext(body, Options::all()));
+        content.push_str(&rendered);
+        content.push_str("</article>\n");
+
+        page(&time, "../", &self.nav("../"), &content)
+    }
+
+    /// A page listing entries, newest first
+    fn list_page(&self, title: &str, root: &str, entries: &[&JrnEntry]) -> String {
+        let mut content = format!("<h1>{}</h1>\n<ul class=\"entries\">\n", escape(title));
+        for entry in entries {
+            let _ = writeln!(
+                content,
+                "<li><a href=\"{}entries/{}.html\">{}</a> {} {}</li>",
+                root,
+                entry.id(),
+                escape(&entry.creation_time.in_zone(self.zone).to_string()),
+                self.tag_links(entry, root),
+                escape(&subject(entry, self.config)),
+            );
+        }
+        content.push_str("</ul>\n");
+        page(title, root, &self.nav(root), &content)
+    }
+
+    /// Links to the index, the tags and every month, newest first
+    fn nav(&self, root: &str) -> String {
+        let mut nav = format!(
+            "<a href=\"{0}index.html\">All {1} entries</a><a href=\"{0}tags.html\">{2} tags</a>",
+            root,
+            self.entries.len(),
+            self.tags.sorted().len(),
+        );
+        for month in self.months.keys().rev() {
+            let _ = write!(nav, "<a href=\"{0}archive/{1}.html\">{1}</a>", root, month);
+        }
+        nav
+    }
+
+    fn meta(&self, entry: &JrnEntry, root: &str) -> String {
+        let mut meta = String::new();
+        if let Some(location) = entry.location.as_option() {
+            let _ = write!(meta, "{} ", escape(location));
+        }
+        meta.push_str(&self.tag_links(entry, root));
+        meta
+    }
+
+    fn tag_links(&self, entry: &JrnEntry, root: &str) -> String {
+        let links: Vec<String> = entry.tags.iter().map(|t| self.tag_link(t, root)).collect();
+        links.join("")
+    }
+
+    fn tag_link(&self, tag: &str, root: &str) -> String {
+        format!("<a class=\"tag\" href=\"{}tags/{}\">{}</a>", root, tag_page(tag), escape(tag))
+    }
+}
+
+fn page(title: &str, root: &str, nav: &str, content: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n\
+         <nav><a href=\"{}index.html\">jrn</a>{}</nav>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        root,
+        nav,
+        content,
+    )
+}
+
+/// Copies the attachments of entry into dir, so links relative to the entry keep working
+fn copy_attachments(entry: &JrnEntry, config: &Settings, dir: &Path) -> Result<(), JrnError> {
+    let attachments = entry.attachment_dir(config);
+    if !attachments.is_dir() {
+        return Ok(());
+    }
+    let target = dir.join(attachments.file_name().unwrap_or_default());
+    fs::create_dir_all(&target)?;
+    for file in fs::read_dir(&attachments)? {
+        let file = file?;
+        if file.file_type()?.is_file() {
+            fs::copy(file.path(), target.join(file.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// The first line of the body of entry with Markdown heading marks removed
+fn subject(entry: &JrnEntry, config: &Settings) -> String {
+    let body = entry.body(config).unwrap_or_default();
+    let line = body.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    String::from(line.trim_start_matches('#').trim())
+}
+
+/// The file name of the page of tag
+///
+/// Characters other than letters, digits, '-' and '.' are written as _ followed by their
+/// code point, so every tag gets a distinct name that needs no escaping in a link
+fn tag_page(tag: &str) -> String {
+    let mut name = String::with_capacity(tag.len() + 5);
+    for c in tag.chars() {
+        if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+            name.push(c);
+        } else {
+            let _ = write!(name, "_{:x}_", c as u32);
+        }
+    }
+    name.push_str(".html");
+    name
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Location, TimeStamp};
+
+    fn write_entry(dir: &Path, name: &str, body: &str, tags: &[&str], t: TimeStamp) -> JrnEntry {
+        let file_path = dir.join(name);
+        fs::write(&file_path, body).unwrap();
+        JrnEntry {
+            creation_time: t,
+            location: Location::default(),
+            tags: tags.iter().map(|t| String::from(*t)).collect(),
+            file_path,
+        }
+    }
+
+    #[test]
+    fn tag_pages_are_distinct() {
+        assert_eq!(tag_page("work/projectx"), "work_2f_projectx.html");
+        assert_ne!(tag_page("a:b"), tag_page("a_b"));
+    }
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(escape("<a href=\"x\">&</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+    }
+
+    #[test]
+    fn writes_site() {
+        let journal = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let at = |month, day| TimeStamp::from_ymdhm(2019, month, day, 9, 0).unwrap();
+        let entries = vec![
+            write_entry(journal.path(), "a", "# Hello\n\n*world*", &["work"], at(9, 30)),
+            write_entry(journal.path(), "b", "second", &["work", "home"], at(10, 1)),
+        ];
+        let mut tags = TagContainer::new();
+        for entry in &entries {
+            for tag in &entry.tags {
+                tags.insert(tag, &entry.file_path);
+            }
+        }
+
+        write_site(out.path(), &entries, &tags, &Settings::default()).unwrap();
+
+        let first = out.path().join("entries").join(format!("{}.html", entries[0].id()));
+        let first = fs::read_to_string(first).unwrap();
+        assert!(first.contains("<h1>Hello</h1>"));
+        assert!(first.contains("<em>world</em>"));
+        assert!(first.contains("../archive/2019-09.html"));
+
+        let index = fs::read_to_string(out.path().join("index.html")).unwrap();
+        assert!(index.find(&entries[1].id()).unwrap() < index.find(&entries[0].id()).unwrap());
+
+        let work = fs::read_to_string(out.path().join("tags").join("work.html")).unwrap();
+        assert!(work.contains(&entries[0].id()) && work.contains(&entries[1].id()));
+        let home = fs::read_to_string(out.path().join("tags").join("home.html")).unwrap();
+        assert!(!home.contains(&entries[0].id()));
+
+        assert!(out.path().join("archive").join("2019-10.html").exists());
+        assert!(out.path().join("tags.html").exists());
+        assert!(!index.contains("http"));
+    }
+}
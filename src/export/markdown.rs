@@ -0,0 +1,129 @@
+//! Concatenates entries into a single Markdown document
+//!
+//! The document starts with a table of contents linking to each entry.
+//! Every entry gets a heading with its date and a line with its location and tags,
+//! headings within the body are moved down two levels to sit below it
+
+use std::io::Write;
+
+use crate::{DisplayZone, JrnEntry, JrnError, Settings};
+
+static HEADING_FORMAT: &str = "%A %-d %B %Y, %H:%M";
+static SHORT_ID_LENGTH: usize = 7;
+
+/// Writes entries to out in the order given
+pub(crate) fn write_markdown<W: Write>(
+    out: &mut W,
+    entries: &[&JrnEntry],
+    config: &Settings,
+) -> Result<(), JrnError> {
+    let zone = config.get_display_zone();
+    writeln!(out, "# Journal")?;
+    writeln!(out)?;
+    writeln!(out, "## Contents")?;
+    writeln!(out)?;
+    for entry in entries {
+        writeln!(out, "- [{}](#{})", heading(entry, zone), anchor(entry))?;
+    }
+
+    for entry in entries {
+        writeln!(out)?;
+        writeln!(out, "<a id=\"{}\"></a>", anchor(entry))?;
+        writeln!(out)?;
+        writeln!(out, "## {}", heading(entry, zone))?;
+        writeln!(out)?;
+
+        let mut meta: Vec<String> = Vec::new();
+        if let Some(location) = entry.location.as_option() {
+            meta.push(format!("Location: {}", location));
+        }
+        if !entry.tags.is_empty() {
+            meta.push(format!("Tags: {}", entry.tags.join(", ")));
+        }
+        if !meta.is_empty() {
+            writeln!(out, "*{}*", meta.join(" · "))?;
+            writeln!(out)?;
+        }
+
+        let body = demote_headings(&entry.body(config)?, 2);
+        writeln!(out, "{}", body.trim_end())?;
+    }
+    Ok(())
+}
+
+fn heading(entry: &JrnEntry, zone: DisplayZone) -> String {
+    entry.creation_time.in_zone(zone).format(HEADING_FORMAT)
+}
+
+/// A link target for entry that is stable across exports
+fn anchor(entry: &JrnEntry) -> String {
+    let id: String = entry.id().chars().take(SHORT_ID_LENGTH).collect();
+    format!("entry-{}", id)
+}
+
+/// Adds levels to every ATX heading of markdown, outside of fenced code blocks
+fn demote_headings(markdown: &str, levels: usize) -> String {
+    let prefix = "#".repeat(levels);
+    let mut fence: Option<&str> = None;
+    let mut result = String::with_capacity(markdown.len());
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) if trimmed.starts_with(marker) => fence = None,
+            Some(_) => (),
+            None if trimmed.starts_with("```") => fence = Some("```"),
+            None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+            None if is_heading(trimmed) => result.push_str(&prefix),
+            None => (),
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+    result
+}
+
+fn is_heading(line: &str) -> bool {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    (1..=6).contains(&level) && line[level..].chars().next().is_none_or(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::{Location, TimeStamp};
+
+    #[test]
+    fn demotes_headings_outside_code() {
+        let markdown = "# Title\ntext #1\n```\n# comment\n```\n##Not a heading\n## Sub\n";
+        assert_eq!(
+            demote_headings(markdown, 2),
+            "### Title\ntext #1\n```\n# comment\n```\n##Not a heading\n#### Sub\n"
+        );
+    }
+
+    #[test]
+    fn writes_contents_and_headings() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path: PathBuf = dir.path().join("entry");
+        std::fs::write(&file_path, "# Review\nwent well\n").unwrap();
+        let entry = JrnEntry {
+            creation_time: TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap(),
+            location: Location::from(String::from("office")),
+            tags: vec![String::from("work"), String::from("review")],
+            file_path,
+        };
+
+        let mut out: Vec<u8> = Vec::new();
+        write_markdown(&mut out, &[&entry], &Settings::default()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let anchor = anchor(&entry);
+        assert!(out.contains(&format!("- [Tuesday 1 October 2019, 09:30](#{})", anchor)));
+        let heading = format!("<a id=\"{}\"></a>\n\n## Tuesday 1 October 2019, 09:30\n", anchor);
+        assert!(out.contains(&heading));
+        assert!(out.contains("*Location: office · Tags: work, review*"));
+        assert!(out.ends_with("### Review\nwent well\n"));
+    }
+}
@@ -0,0 +1,7 @@
+//! Writes the journal out in formats meant for reading elsewhere
+
+mod html;
+mod markdown;
+
+pub(crate) use html::write_site;
+pub(crate) use markdown::write_markdown;
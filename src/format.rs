@@ -0,0 +1,203 @@
+//! Templates for displaying entries on a single line, in the spirit of git's --pretty
+//!
+//! A template is plain text with placeholders
+//!     %H  the id of the entry
+//!     %h  the id shortened to its first 7 characters
+//!     %t  the creation time
+//!     %l  the location
+//!     %g  the tags, separated by spaces
+//!     %f  the path of the entry
+//!     %s  the first line of the body
+//!     %b  the whole body
+//!     %n  a newline
+//!     %%  a literal %
+//! Any other % sequence is written as is
+
+use std::fmt::{self, Write};
+
+use super::{JrnEntry, Settings};
+use crate::color::{paint, Style};
+
+static SHORT_ID_LENGTH: usize = 7;
+static ONELINE: &str = "%h %t %g %s";
+
+/// A parsed display template, see the module docs for the placeholders
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Id,
+    ShortId,
+    Time,
+    Location,
+    Tags,
+    Path,
+    Subject,
+    Body,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+            let part = match chars.next() {
+                Some('H') => Part::Id,
+                Some('h') => Part::ShortId,
+                Some('t') => Part::Time,
+                Some('l') => Part::Location,
+                Some('g') => Part::Tags,
+                Some('f') => Part::Path,
+                Some('s') => Part::Subject,
+                Some('b') => Part::Body,
+                Some('n') => {
+                    literal.push('\n');
+                    continue;
+                }
+                Some('%') => {
+                    literal.push('%');
+                    continue;
+                }
+                Some(other) => {
+                    literal.push('%');
+                    literal.push(other);
+                    continue;
+                }
+                None => {
+                    literal.push('%');
+                    break;
+                }
+            };
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(part);
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Template { parts }
+    }
+
+    /// The template used by list --oneline, the short id, time, tags and first line
+    pub fn oneline() -> Self {
+        Template::parse(ONELINE)
+    }
+
+    /// Writes entry following this template
+    ///
+    /// The body is only read if the template uses it
+    /// The id, time, location and tags are coloured if color is true
+    pub(crate) fn write<W: Write>(
+        &self,
+        entry: &JrnEntry,
+        config: &Settings,
+        color: bool,
+        f: &mut W,
+    ) -> fmt::Result {
+        let zone = config.get_display_zone();
+        let mut body: Option<String> = None;
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => f.write_str(s)?,
+                Part::Id => write!(f, "{}", paint(entry.id(), Style::Id, color))?,
+                Part::ShortId => {
+                    let id: String = entry.id().chars().take(SHORT_ID_LENGTH).collect();
+                    write!(f, "{}", paint(id, Style::Id, color))?
+                }
+                Part::Time => {
+                    let time = entry.creation_time.in_zone(zone);
+                    write!(f, "{}", paint(time, Style::Time, color))?
+                }
+                Part::Location => write!(f, "{}", paint(&entry.location, Style::Location, color))?,
+                Part::Tags => write!(f, "{}", paint(entry.tags.join(" "), Style::Tag, color))?,
+                Part::Path => write!(f, "{}", entry.file_path.display())?,
+                Part::Subject => {
+                    let body = body.get_or_insert_with(|| entry.body_or_error(config));
+                    f.write_str(body.lines().next().unwrap_or(""))?
+                }
+                Part::Body => f.write_str(body.get_or_insert_with(|| entry.body_or_error(config)))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::{Location, TimeStamp};
+
+    fn entry(file_path: PathBuf) -> JrnEntry {
+        JrnEntry {
+            creation_time: TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap(),
+            location: Location::default(),
+            tags: vec![String::from("work"), String::from("person:alice")],
+            file_path,
+        }
+    }
+
+    fn render(template: &str, entry: &JrnEntry) -> String {
+        let mut out = String::new();
+        Template::parse(template).write(entry, &Settings::default(), false, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn parses_placeholders() {
+        let template = Template::parse("[%t] %g%n");
+        assert_eq!(template.parts, vec![
+            Part::Literal(String::from("[")),
+            Part::Time,
+            Part::Literal(String::from("] ")),
+            Part::Tags,
+            Part::Literal(String::from("\n")),
+        ]);
+    }
+
+    #[test]
+    fn keeps_unknown_sequences() {
+        let entry = entry(PathBuf::from("/none"));
+        assert_eq!(render("100%% %q %", &entry), "100% %q %");
+    }
+
+    #[test]
+    fn writes_subject_and_body() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("entry");
+        std::fs::write(&path, "first line\nsecond line\n").unwrap();
+        let entry = entry(path);
+
+        assert_eq!(render("%s", &entry), "first line");
+        assert_eq!(render("%b", &entry), "first line\nsecond line\n");
+        assert_eq!(
+            render("%h %t %g %s", &entry),
+            format!("{} 2019-10-01_0930 work person:alice first line", &entry.id()[..7])
+        );
+    }
+
+    #[test]
+    fn missing_body_does_not_panic() {
+        let entry = entry(PathBuf::from("/nonexistent/entry"));
+        assert!(render("%s", &entry).starts_with("[cannot read"));
+    }
+
+    #[test]
+    fn colours_metadata() {
+        let entry = entry(PathBuf::from("/none"));
+        let mut out = String::new();
+        Template::parse("%g").write(&entry, &Settings::default(), true, &mut out).unwrap();
+        assert_eq!(out, "\x1b[36mwork person:alice\x1b[0m");
+    }
+}
@@ -0,0 +1,69 @@
+//! Folders of Markdown files named by date, 2019-10-01.md
+//!
+//! Folders are searched recursively, each file becomes an entry at midnight of its date.
+//! Files not named by a date are skipped with a warning
+
+use std::fs;
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveTime};
+
+use super::Record;
+use crate::{JrnError, TimeStamp};
+
+static EXTENSION: &str = "md";
+
+pub(crate) fn read(path: &Path) -> Result<Vec<Record>, JrnError> {
+    let mut records = Vec::new();
+    collect(path, &mut records)?;
+    Ok(records)
+}
+
+fn collect(path: &Path, records: &mut Vec<Record>) -> Result<(), JrnError> {
+    if path.is_dir() {
+        for child in fs::read_dir(path)? {
+            collect(&child?.path(), records)?;
+        }
+        return Ok(());
+    }
+
+    if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+        return Ok(());
+    }
+    let date = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok());
+    match date {
+        Some(date) => records.push(Record {
+            creation_time: TimeStamp::from_naive(date.and_time(NaiveTime::MIN)),
+            tags: Vec::new(),
+            location: None,
+            body: fs::read_to_string(path)?,
+        }),
+        None => log::warn!("Skipping {}, it is not named by a date", path.display()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_dated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("2019")).unwrap();
+        fs::write(dir.path().join("2019").join("2019-10-01.md"), "first").unwrap();
+        fs::write(dir.path().join("2019-10-02.md"), "second").unwrap();
+        fs::write(dir.path().join("notes.md"), "skipped").unwrap();
+        fs::write(dir.path().join("2019-10-03.txt"), "skipped").unwrap();
+
+        let mut records = read(dir.path()).unwrap();
+        records.sort_by_key(|r| r.creation_time);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].creation_time, TimeStamp::from_ymdhm(2019, 10, 1, 0, 0).unwrap());
+        assert_eq!(records[0].body, "first");
+        assert_eq!(records[1].body, "second");
+    }
+}
@@ -0,0 +1,103 @@
+//! Day One JSON exports
+//!
+//! Only the fields jrn can use are read
+//!     {
+//!         "entries": [{
+//!             "creationDate": "2019-10-01T07:30:00Z",
+//!             "text": "...",
+//!             "tags": ["work"],
+//!             "location": { "placeName": "Office", "localityName": "Berlin" }
+//!         }]
+//!     }
+//! Times are stored in UTC and converted to the local time zone
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use super::Record;
+use crate::{JrnError, TimeStamp};
+
+#[derive(Deserialize)]
+struct Export {
+    entries: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    creation_date: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    location: Option<Location>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    place_name: Option<String>,
+    locality_name: Option<String>,
+}
+
+pub(crate) fn read(json: &str) -> Result<Vec<Record>, JrnError> {
+    let export: Export = serde_json::from_str(json)?;
+    export
+        .entries
+        .into_iter()
+        .map(|entry| {
+            let created = DateTime::parse_from_rfc3339(&entry.creation_date)
+                .map_err(|_| format!("invalid creationDate {}", entry.creation_date))
+                .map_err(JrnError::InvalidImport)?
+                .with_timezone(&Local);
+            let location = entry.location.and_then(|l| l.place_name.or(l.locality_name));
+            let mut body = entry.text;
+            if !body.ends_with('\n') {
+                body.push('\n');
+            }
+            Ok(Record {
+                creation_time: TimeStamp::from_naive(created.naive_local())
+                    .with_offset(*created.offset()),
+                tags: entry.tags,
+                location,
+                body,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_export() {
+        let json = r#"{
+            "metadata": { "version": "1.0" },
+            "entries": [
+                {
+                    "creationDate": "2019-10-01T07:30:00Z",
+                    "text": "Standup",
+                    "tags": ["work"],
+                    "location": { "localityName": "Berlin" },
+                    "uuid": "ABC"
+                },
+                { "creationDate": "2019-10-02T07:30:00Z" }
+            ]
+        }"#;
+        let records = read(json).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].body, "Standup\n");
+        assert_eq!(records[0].tags, vec!["work"]);
+        assert_eq!(records[0].location.as_deref(), Some("Berlin"));
+        let utc = DateTime::parse_from_rfc3339("2019-10-01T07:30:00Z").unwrap();
+        assert_eq!(records[0].creation_time.to_utc(), utc);
+        assert_eq!(records[1].location, None);
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        let json = r#"{ "entries": [{ "creationDate": "yesterday" }] }"#;
+        assert!(read(json).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! jrnl.sh plain text journals
+//!
+//! Every entry starts with a line holding its time and title, the body follows
+//! until the next such line. Tags are words starting with @ anywhere in the entry
+//!     [2019-10-01 09:30] Planning @work
+//!     2019-10-01 09:30 Planning @work
+//!     [2019-10-01 09:30:00 AM] Planning @work
+//! A * right after the time marks a starred entry, which is tagged starred
+
+use chrono::NaiveDateTime;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::Record;
+use crate::TimeStamp;
+
+static TAG_SYMBOL: char = '@';
+static STARRED_TAG: &str = "starred";
+static TIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %I:%M %p",
+    "%Y-%m-%d %I:%M:%S %p",
+];
+
+pub(crate) fn read(journal: &str) -> Vec<Record> {
+    lazy_static! {
+        static ref HEADER: Regex = Regex::new(
+            r"(?x)
+            ^\[?
+            (?P<time>\d{4}-\d{2}-\d{2}[\ T]\d{1,2}:\d{2}(?::\d{2})?(?:\ ?[AaPp][Mm])?)
+            \]?
+            (?P<star>\ ?\*)?
+            (?:\ (?P<title>.*))?$"
+        ).unwrap();
+    }
+
+    let mut records: Vec<Record> = Vec::new();
+    let mut starred = false;
+    for line in journal.lines() {
+        let header = HEADER
+            .captures(line)
+            .and_then(|caps| parse_time(&caps["time"]).map(|t| (t, caps)));
+        match header {
+            Some((creation_time, caps)) => {
+                finish(records.last_mut(), starred);
+                starred = caps.name("star").is_some();
+                records.push(Record {
+                    creation_time,
+                    tags: Vec::new(),
+                    location: None,
+                    body: format!("{}\n", caps.name("title").map_or("", |t| t.as_str())),
+                });
+            }
+            None => {
+                if let Some(record) = records.last_mut() {
+                    record.body.push_str(line);
+                    record.body.push('\n');
+                }
+            }
+        }
+    }
+    finish(records.last_mut(), starred);
+    records
+}
+
+/// Trims the body and collects the tags of a finished record
+fn finish(record: Option<&mut Record>, starred: bool) {
+    if let Some(record) = record {
+        let trimmed = record.body.trim_end().len();
+        record.body.truncate(trimmed);
+        record.body.push('\n');
+        record.tags = tags(&record.body);
+        if starred {
+            record.tags.push(String::from(STARRED_TAG));
+        }
+    }
+}
+
+fn parse_time(s: &str) -> Option<TimeStamp> {
+    let s = s.replace('T', " ");
+    TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&s, format).ok())
+        .map(TimeStamp::from_naive)
+}
+
+/// Every distinct word starting with @, without trailing punctuation
+fn tags(body: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in body.split_whitespace() {
+        if let Some(tag) = word.strip_prefix(TAG_SYMBOL) {
+            let tag = tag.trim_end_matches(|c: char| !c.is_alphanumeric());
+            if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                tags.push(String::from(tag));
+            }
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_entries() {
+        let journal = "\
+[2019-10-01 09:30] Planning @work
+Talked to @alice.
+
+2019-10-02 18:00 * Dinner
+[2019-10-03 09:15:00 PM] Late
+";
+        let records = read(journal);
+        assert_eq!(records.len(), 3);
+
+        assert_eq!(records[0].creation_time, TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap());
+        assert_eq!(records[0].body, "Planning @work\nTalked to @alice.\n");
+        assert_eq!(records[0].tags, vec!["work", "alice"]);
+
+        assert_eq!(records[1].body, "Dinner\n");
+        assert_eq!(records[1].tags, vec!["starred"]);
+
+        assert_eq!(records[2].creation_time, TimeStamp::from_ymdhm(2019, 10, 3, 21, 15).unwrap());
+    }
+
+    #[test]
+    fn ignores_text_before_first_entry() {
+        assert!(read("just some notes\n").is_empty());
+    }
+}
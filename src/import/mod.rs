@@ -0,0 +1,71 @@
+//! Reads entries written by other journaling tools
+//!
+//! Each format is parsed into [Record]s, which the repo turns into entries
+
+mod dated;
+mod dayone;
+mod jrnl;
+
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{JrnError, TimeStamp};
+
+/// An entry read from another tool, not yet written to the repo
+#[derive(Debug, PartialEq)]
+pub(crate) struct Record {
+    pub creation_time: TimeStamp,
+    pub tags: Vec<String>,
+    pub location: Option<String>,
+    pub body: String,
+}
+
+/// What became of a record read by [crate::JrnRepo::import]
+#[derive(Debug, PartialEq)]
+pub struct Imported {
+    /// the file name of the entry
+    pub name: String,
+    /// true if an entry already existed at the same time
+    pub skipped: bool,
+}
+
+/// The formats entries can be imported from
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ImportFormat {
+    /// a jrnl.sh plain text journal, entries start with their date and tags are marked with @
+    Jrnl,
+    /// a Day One JSON export
+    DayOne,
+    /// a folder of Markdown files named by their date, 2019-10-01.md
+    Dated,
+}
+
+impl ImportFormat {
+    pub fn names() -> &'static [&'static str] {
+        &["jrnl", "dayone", "md"]
+    }
+
+    /// Reads every record found at path, oldest first
+    pub(crate) fn read(self, path: &Path) -> Result<Vec<Record>, JrnError> {
+        let mut records = match self {
+            ImportFormat::Jrnl => jrnl::read(&std::fs::read_to_string(path)?),
+            ImportFormat::DayOne => dayone::read(&std::fs::read_to_string(path)?)?,
+            ImportFormat::Dated => dated::read(path)?,
+        };
+        records.sort_by_key(|r| r.creation_time);
+        Ok(records)
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = JrnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "jrnl" => Ok(ImportFormat::Jrnl),
+            "dayone" | "day-one" => Ok(ImportFormat::DayOne),
+            "md" | "dated" => Ok(ImportFormat::Dated),
+            _ => Err(JrnError::InvalidImport(format!("unknown format {}", s))),
+        }
+    }
+}
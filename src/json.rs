@@ -0,0 +1,191 @@
+//! Machine readable output
+//!
+//! With --json a command writes a json array of values, with --ndjson it writes
+//! one value per line. show and stats write a single value either way
+//!
+//! An entry is written as
+//!     {
+//!         "id": "6475c3a45901d70",
+//!         "timestamp": "2019-10-01T09:30:00+02:00",
+//!         "location": "home",
+//!         "tags": ["work", "person:alice"],
+//!         "path": "/home/me/journal/2019-10-01_0930-work_person=alice",
+//!         "body": "..."
+//!     }
+//! the timestamp only carries an offset if one was recorded, the location is null
+//! if none was recorded and the body is only present when it was asked for
+//!
+//! A tag is written as
+//!     { "tag": "work", "count": 3, "entries": ["..."] }
+//! where entries is only present when listing with --entries
+//!
+//! A setting is written as
+//!     { "setting": "Editor", "value": "vim" }
+//!
+//! Statistics are written as
+//!     {
+//!         "entries": 42, "words": 9001, "first": "2019-10-01", "last": "2019-12-24",
+//!         "days_written": 30, "current_streak": 2, "longest_streak": 9,
+//!         "per_day": { "2019-10-01": 2, ... }, "per_week": { "2019-W40": 5, ... },
+//!         "per_month": { "2019-10": 14, ... },
+//!         "tags": [{ "tag": "work", "count": 20 }, ...],
+//!         "locations": [{ "location": "Berlin", "count": 12 }, ...]
+//!     }
+//!
+//! Fields may be added to these objects, existing fields will not change
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use super::{CountAndTag, JrnEntry, JrnError, Settings};
+use crate::config::JrnSetting;
+
+/// How a command writes its results
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Output {
+    /// human readable text
+    Text,
+    /// a single json value
+    Json,
+    /// one json value per line
+    Ndjson,
+}
+
+impl Serialize for JrnEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("JrnEntry", 5)?;
+        state.serialize_field("id", &self.id())?;
+        state.serialize_field("timestamp", &self.creation_time.to_iso8601())?;
+        state.serialize_field("location", &self.location.as_option())?;
+        state.serialize_field("tags", &self.tags)?;
+        state.serialize_field("path", &self.file_path)?;
+        state.end()
+    }
+}
+
+impl Serialize for CountAndTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CountAndTag", 2)?;
+        state.serialize_field("tag", &self.1)?;
+        state.serialize_field("count", &self.0)?;
+        state.end()
+    }
+}
+
+/// An entry, optionally with its body
+#[derive(serde::Serialize)]
+pub struct EntryJson<'a> {
+    #[serde(flatten)]
+    pub entry: &'a JrnEntry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+impl<'a> EntryJson<'a> {
+    pub fn new(entry: &'a JrnEntry, body: bool, config: &Settings) -> Result<Self, JrnError> {
+        let body = match body {
+            true => Some(entry.body(config)?),
+            false => None,
+        };
+        Ok(EntryJson { entry, body })
+    }
+}
+
+/// A tag, optionally with the entries it appears in
+#[derive(serde::Serialize)]
+pub struct TagJson<'a> {
+    #[serde(flatten)]
+    pub tag: &'a CountAndTag,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries: Option<&'a [PathBuf]>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SettingJson<'a> {
+    pub setting: &'a JrnSetting,
+    pub value: &'a str,
+}
+
+/// Writes values as a json array, or one per line for [Output::Ndjson]
+pub fn write_all<W, T, I>(out: &mut W, output: Output, values: I) -> Result<(), JrnError>
+where
+    W: Write,
+    T: Serialize,
+    I: IntoIterator<Item = T>,
+{
+    if output == Output::Ndjson {
+        for value in values {
+            serde_json::to_writer(&mut *out, &value)?;
+            writeln!(out)?;
+        }
+    } else {
+        let values: Vec<T> = values.into_iter().collect();
+        serde_json::to_writer_pretty(&mut *out, &values)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Writes a single value, on one line for [Output::Ndjson]
+pub fn write_one<W: Write, T: Serialize>(
+    out: &mut W,
+    output: Output,
+    value: &T,
+) -> Result<(), JrnError> {
+    if output == Output::Ndjson {
+        serde_json::to_writer(&mut *out, value)?;
+    } else {
+        serde_json::to_writer_pretty(&mut *out, value)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Location, TimeStamp};
+
+    fn entry() -> JrnEntry {
+        JrnEntry {
+            creation_time: TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap(),
+            location: Location::default(),
+            tags: vec![String::from("work")],
+            file_path: PathBuf::from("/journal/2019-10-01_0930-work"),
+        }
+    }
+
+    #[test]
+    fn entry_schema() {
+        let entry = entry();
+        let value = serde_json::to_value(EntryJson { entry: &entry, body: None }).unwrap();
+        assert_eq!(value, serde_json::json!({
+            "id": entry.id(),
+            "timestamp": "2019-10-01T09:30:00",
+            "location": null,
+            "tags": ["work"],
+            "path": "/journal/2019-10-01_0930-work",
+        }));
+
+        let with_body = EntryJson { entry: &entry, body: Some(String::from("hello")) };
+        assert_eq!(serde_json::to_value(with_body).unwrap()["body"], "hello");
+    }
+
+    #[test]
+    fn tag_schema() {
+        let tag = CountAndTag(3, String::from("work"));
+        let value = serde_json::to_value(TagJson { tag: &tag, entries: None }).unwrap();
+        assert_eq!(value, serde_json::json!({ "tag": "work", "count": 3 }));
+    }
+
+    #[test]
+    fn ndjson_writes_a_line_per_value() {
+        let tags = vec![CountAndTag(2, String::from("a")), CountAndTag(1, String::from("b"))];
+        let mut out: Vec<u8> = Vec::new();
+        write_all(&mut out, Output::Ndjson, &tags).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "{\"tag\":\"a\",\"count\":2}\n{\"tag\":\"b\",\"count\":1}\n");
+    }
+}
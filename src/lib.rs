@@ -1,3 +1,11 @@
+//! The stupid journal system, as a library
+//!
+//! [JrnRepo::open] reads the journal in a directory,
+//! along with its .jrnconfig, .jrnignore and .jrnaliases.
+//! The repo returns entries, tags and reports as data and never writes to std::out,
+//! presenting them is left to the caller as the jrn command line does.
+//! Commands that change several entries hand a [Plan] of their changes to a callback
+//! that decides whether to go ahead
 extern crate chrono;
 extern crate dirs;
 extern crate log;
@@ -5,23 +13,45 @@ extern crate ron;
 extern crate serde;
 extern crate simplelog;
 
+mod calendar;
+mod color;
 mod config;
+mod crypt;
 mod entry;
 mod error;
+mod export;
 mod deliminate;
+mod format;
+mod import;
+pub mod json;
+mod link;
 mod location;
+mod plan;
 mod repo;
+mod stats;
+pub mod tag;
 mod tag_container;
 mod time;
+mod trash;
+mod undo;
+mod walk;
 
 //internals
-use entry::JrnEntry;
-use location::Location;
-pub use tag_container::{CountAndTag, TagContainer};
-use time::TimeStamp;
 use deliminate::Deliminated;
 
 //exports
-pub use config::{IgnorePatterns, Settings};
+pub use calendar::Calendar;
+pub use color::{paint, ColorChoice, Painted, Style};
+pub use config::{IgnorePatterns, JrnSetting, Settings, TagAliases};
+pub use entry::{EntryDisplay, JrnEntry};
 pub use error::JrnError;
-pub use repo::JrnRepo;
+pub use format::Template;
+pub use import::{ImportFormat, Imported};
+pub use json::Output;
+pub use location::Location;
+pub use plan::Plan;
+pub use repo::{EntryQuery, JrnRepo};
+pub use stats::{LocationCount, Stats};
+pub use tag_container::{CountAndTag, TagContainer, TagNode};
+pub use time::{DisplayZone, TimeStamp, TimestampFormat};
+pub use trash::Item as TrashItem;
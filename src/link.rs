@@ -0,0 +1,289 @@
+//! Links between entries, written wiki style in their text
+//!
+//! A link names its target by the name of the entry, by its time alone, or by a prefix of its id
+//!     [[2019-10-01_0930-work]]
+//!     [[2019-10-01_0930]]
+//!     [[id:3f2a9c1]]
+//! Links are kept in the same form when their target is renamed, see [rewrite]
+//! and entries known to contain no links are not read for that, see [LinkIndex]
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{JrnEntry, Settings};
+use crate::config::JRN_DIR;
+
+static ID_PREFIX: &str = "id:";
+static INDEX_FILE_NAME: &str = "links.json";
+
+/// What a link refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Target<'a> {
+    /// a prefix of an entry id
+    Id(&'a str),
+    /// the name or time of an entry
+    Name(&'a str),
+}
+
+impl fmt::Display for Target<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Id(id) => write!(f, "[[{}{}]]", ID_PREFIX, id),
+            Target::Name(name) => write!(f, "[[{}]]", name),
+        }
+    }
+}
+
+/// A link found in the text of an entry
+#[derive(Debug, PartialEq)]
+pub(crate) struct Link<'a> {
+    pub target: Target<'a>,
+    /// the position of the target in the text, without the brackets
+    range: Range<usize>,
+}
+
+/// Every link in text, in order
+pub(crate) fn parse(text: &str) -> Vec<Link<'_>> {
+    lazy_static! {
+        static ref LINK: Regex = Regex::new(r"\[\[\s*([^\[\]\n]+?)\s*\]\]").unwrap();
+    }
+
+    LINK.captures_iter(text)
+        .filter_map(|caps| caps.get(1))
+        .map(|m| {
+            let target = match m.as_str().strip_prefix(ID_PREFIX) {
+                Some(id) => Target::Id(id.trim()),
+                None => Target::Name(m.as_str()),
+            };
+            Link { target, range: m.start()..m.end() }
+        })
+        .filter(|link| link.target != Target::Id(""))
+        .collect()
+}
+
+/// The ways an entry can be linked to, used to find links to it once it is renamed
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Names {
+    id: String,
+    name: String,
+    time: String,
+}
+
+impl Names {
+    pub fn of(entry: &JrnEntry, config: &Settings) -> Self {
+        Names {
+            id: entry.id(),
+            name: entry.file_name(config),
+            time: time_name(entry, config),
+        }
+    }
+}
+
+/// The time an entry is linked to by, its wall clock time without any recorded utc offset
+pub(crate) fn time_name(entry: &JrnEntry, config: &Settings) -> String {
+    config.get_timestamp_format().format_wall_clock(&entry.creation_time)
+}
+
+/// The ids of entries before they were renamed, from their ids now
+pub(crate) fn previous_ids(ids: Vec<String>, renamed: &[(Names, Names)]) -> Vec<String> {
+    ids.into_iter()
+        .map(|id| match renamed.iter().find(|(_, new)| new.id == id) {
+            Some((old, _)) => old.id.clone(),
+            None => id,
+        })
+        .collect()
+}
+
+/// Rewrites the links in text to entries once known by the first names of a pair,
+/// so they refer to them by the second
+///
+/// Each link keeps its form, an id prefix keeps its length.
+/// An id prefix is only rewritten if it matched no other of ids, the ids before the renames.
+/// Returns None if no link was changed
+pub(crate) fn rewrite(text: &str, renamed: &[(Names, Names)], ids: &[String]) -> Option<String> {
+    if !text.contains("[[") {
+        return None;
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    for link in parse(text) {
+        let replacement =
+            renamed.iter().find_map(|(old, new)| replace(link.target, old, new, ids));
+        let replacement = match replacement {
+            Some(replacement) => replacement,
+            None => continue,
+        };
+        result.push_str(&text[copied..link.range.start]);
+        result.push_str(&replacement);
+        copied = link.range.end;
+    }
+    result.push_str(&text[copied..]);
+
+    match result != text {
+        true => Some(result),
+        false => None,
+    }
+}
+
+/// The target to link to the entry once known as old by, in the form of target
+fn replace(target: Target, old: &Names, new: &Names, ids: &[String]) -> Option<String> {
+    let unique = |prefix| ids.iter().filter(|id| id.starts_with(prefix)).count() == 1;
+    match target {
+        Target::Id(prefix) if old.id.starts_with(prefix) && unique(prefix) => {
+            let len = prefix.len().min(new.id.len());
+            Some(format!("{}{}", ID_PREFIX, &new.id[..len]))
+        }
+        Target::Name(name) if name == old.name => Some(new.name.clone()),
+        Target::Name(name) if name == old.time => Some(new.time.clone()),
+        _ => None,
+    }
+}
+
+/// Whether an entry contained links when it was last read
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Seen {
+    modified: SystemTime,
+    links: bool,
+}
+
+/// The entries known to contain no links, kept in .jrn/links.json
+/// so they are not read again when an entry is renamed, until their file is modified
+pub(crate) struct LinkIndex {
+    path: PathBuf,
+    /// the paths of entries relative to the root of the repo, and what was seen in them
+    seen: BTreeMap<PathBuf, Seen>,
+}
+
+impl LinkIndex {
+    /// The index of the repo at root, empty if it does not exist or can not be read
+    pub fn at(root: &Path) -> Self {
+        let path = root.join(JRN_DIR).join(INDEX_FILE_NAME);
+        let seen = match fs::read(&path) {
+            Ok(json) => serde_json::from_slice(&json).unwrap_or_else(|e| {
+                log::warn!("Ignoring {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                log::warn!("Ignoring {}: {}", path.display(), e);
+                BTreeMap::new()
+            }
+        };
+        LinkIndex { path, seen }
+    }
+
+    /// Whether the entry at path, modified at modified, may contain links
+    pub fn may_link(&self, path: &Path, modified: SystemTime) -> bool {
+        self.seen.get(path) != Some(&Seen { modified, links: false })
+    }
+
+    /// Records whether the entry at path, modified at modified, contains links
+    pub fn insert(&mut self, path: PathBuf, modified: SystemTime, links: bool) {
+        self.seen.insert(path, Seen { modified, links });
+    }
+
+    /// Forgets every entry whose path is not kept
+    pub fn retain(&mut self, keep: impl Fn(&Path) -> bool) {
+        self.seen.retain(|path, _| keep(path));
+    }
+
+    /// Writes the index, failing to do so is only warned about as it is rebuilt when missing
+    pub fn save(&self) {
+        let written = serde_json::to_vec(&self.seen)
+            .map_err(io::Error::from)
+            .and_then(|json| {
+                if let Some(dir) = self.path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(&self.path, json)
+            });
+        if let Err(e) = written {
+            log::warn!("Can not write {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(id: &str, name: &str, time: &str) -> Names {
+        Names { id: String::from(id), name: String::from(name), time: String::from(time) }
+    }
+
+    #[test]
+    fn parses_links() {
+        let text = "see [[2019-10-01_0930]] and [[ id:3f2a ]], not [[id:]] or [single]";
+        let targets: Vec<Target> = parse(text).into_iter().map(|l| l.target).collect();
+        assert_eq!(targets, vec![Target::Name("2019-10-01_0930"), Target::Id("3f2a")]);
+    }
+
+    #[test]
+    fn rewrites_links_in_their_form() {
+        let renamed = [(
+            names("3f2a9c1d", "2019-10-01_0930-work", "2019-10-01_0930"),
+            names("77b0e4aa", "2019-10-02_0930-work_home", "2019-10-02_0930"),
+        )];
+        let ids = [String::from("3f2a9c1d"), String::from("99990000")];
+        let text = "[[id:3f2a]] [[2019-10-01_0930-work]] [[ 2019-10-01_0930 ]] [[id:9999]]";
+        assert_eq!(
+            rewrite(text, &renamed, &ids).unwrap(),
+            "[[id:77b0]] [[2019-10-02_0930-work_home]] [[ 2019-10-02_0930 ]] [[id:9999]]"
+        );
+        assert_eq!(rewrite("[[id:9999]]", &renamed, &ids), None);
+        assert_eq!(rewrite("no links", &renamed, &ids), None);
+    }
+
+    #[test]
+    fn keeps_ambiguous_id_prefixes() {
+        let renamed = [(names("3f2a9c1d", "a", "a"), names("77b0e4aa", "b", "b"))];
+        let ids = previous_ids(vec![String::from("77b0e4aa"), String::from("3f2b0000")], &renamed);
+        assert_eq!(ids, vec!["3f2a9c1d", "3f2b0000"]);
+        assert_eq!(rewrite("[[id:3f2]]", &renamed, &ids), None);
+        assert_eq!(rewrite("[[id:3f2a]]", &renamed, &ids).unwrap(), "[[id:77b0]]");
+    }
+
+    #[test]
+    fn rewrites_links_to_several_entries_at_once() {
+        let renamed = [
+            (
+                names("aaaa", "2019-10-01_0930-a", "2019-10-01_0930"),
+                names("bbbb", "2019-10-01_0930-b", "2019-10-01_0930"),
+            ),
+            (
+                names("cccc", "2019-10-02_0930-c", "2019-10-02_0930"),
+                names("dddd", "2019-10-02_0930-d", "2019-10-02_0930"),
+            ),
+        ];
+        let ids = [String::from("aaaa"), String::from("cccc")];
+        let text = "[[2019-10-01_0930-a]] [[id:cc]]";
+        assert_eq!(rewrite(text, &renamed, &ids).unwrap(), "[[2019-10-01_0930-b]] [[id:dd]]");
+    }
+
+    #[test]
+    fn indexes_entries_without_links() {
+        let root = tempfile::tempdir().unwrap();
+        let (plain, linking) = (PathBuf::from("2019-10-01_0930"), PathBuf::from("2019-10-02_0930"));
+        let (then, later) = (SystemTime::UNIX_EPOCH, SystemTime::now());
+        let mut index = LinkIndex::at(root.path());
+        index.insert(plain.clone(), then, false);
+        index.insert(linking.clone(), then, true);
+        index.save();
+
+        let mut index = LinkIndex::at(root.path());
+        assert!(!index.may_link(&plain, then));
+        assert!(index.may_link(&plain, later));
+        assert!(index.may_link(&linking, then));
+        index.retain(|path| path != plain);
+        assert!(index.may_link(&plain, then));
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt::{self, Display, Formatter};
 use crate::JrnRepo;
 
+/// starts the line recording the location of a plain entry, see [Location::split_header]
+static HEADER: &str = "location: ";
+
 #[derive(Debug, Eq, PartialOrd, PartialEq, Ord, Hash)]
 pub struct Location(String);
 
@@ -24,6 +27,37 @@ impl From<String> for Location {
 }
 
 impl Location {
+    /// Splits text into the location recorded in its first line and the text below it
+    ///
+    /// Text without such a line has no recorded location
+    pub(crate) fn split_header(text: &str) -> (Location, &str) {
+        let rest = match text.strip_prefix(HEADER) {
+            Some(rest) => rest,
+            None => return (Location::default(), text),
+        };
+        let (location, body) = rest.split_once('\n').unwrap_or((rest, ""));
+        (Location(String::from(location.trim_end())), body)
+    }
+
+    /// text below a line recording this location, reversing [Location::split_header]
+    ///
+    /// Nothing is added if no location is recorded
+    pub(crate) fn with_header(&self, text: &str) -> String {
+        match self.as_option() {
+            Some(location) => format!("{}{}\n{}", HEADER, location.replace('\n', " "), text),
+            None => String::from(text),
+        }
+    }
+
+    /// The recorded location, None if no location was recorded
+    pub fn as_option(&self) -> Option<&str> {
+        if *self == Location::default() {
+            None
+        } else {
+            Some(&self.0)
+        }
+    }
+
     /// First, if given via commandline return that
     /// Second, return the location found in the repositories configuration if available
     /// Third, return the repositories latest location if one exists
@@ -31,10 +65,8 @@ impl Location {
     pub fn configured_from(arg: Option<String>, repo: &JrnRepo) -> Self {
         if let Some(arg) = arg {
             Location(arg)
-        } else if let Some(loc) = repo.get_location() {
-            loc
         } else {
-            Location::default()
+            repo.get_location().unwrap_or_default()
         }
     }
 }
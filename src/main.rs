@@ -1,8 +1,17 @@
 extern crate clap;
 extern crate structopt;
-use clap::AppSettings;
+use clap::{AppSettings, Arg, Shell};
+use std::io::Write;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
+mod browse;
+mod completions;
+mod confirm;
+mod pager;
+mod print;
+
+use confirm::Confirm;
 use jrn::*;
 use simplelog::SimpleLogger;
 use log::LevelFilter;
@@ -10,17 +19,32 @@ use log::LevelFilter;
 fn main() {
     SimpleLogger::init(LevelFilter::Info, simplelog::Config::default()).unwrap();
 
+    let (jrn, color) = Jrn::build_app();
+    jrn.start_loop(color);
+}
+
+/// The configuration, ignore patterns and tag aliases found for the current working dir
+fn configuration() -> (Settings, IgnorePatterns, TagAliases) {
     let cfg = Settings::find_or_default();
     log::trace!("configuration successfully loaded");
 
     let ignore = IgnorePatterns::find_or_default();
     log::trace!("ignored patterns successfully loaded");
 
-    let repo = JrnRepo::init(cfg, ignore).expect("Failure init repo");
-    log::trace!("Opening repository at {:?}", &repo.root_path);
+    let aliases = TagAliases::find_or_default(&cfg);
+    log::trace!("tag aliases successfully loaded");
+    (cfg, ignore, aliases)
+}
 
-    Jrn::build_app()
-        .start_loop(repo);
+/// Opens the repository in the current working dir, with the configuration found for it
+fn open_repo(color: Option<ColorChoice>) -> Result<JrnRepo, JrnError> {
+    let (cfg, ignore, aliases) = configuration();
+    let mut repo = JrnRepo::init(cfg, ignore, aliases)?;
+    log::trace!("Opening repository at {:?}", &repo.root_path);
+    if let Some(color) = color {
+        repo.set_color(color);
+    }
+    Ok(repo)
 }
 
 #[derive(Debug, StructOpt)]
@@ -43,9 +67,16 @@ enum Jrn {
         /// The location can be pulled from the command line, or the environment
         /// The command line will override the environment configs
         ///
-        /// If no location is found location will be recorded as 'None'
+        /// The location is recorded in the first line of the entry, as location: Berlin.
+        /// Encrypted entries record no location
         location: Option<String>,
-        //TODO implement location handling
+
+        #[structopt(long)]
+        /// Create the entry at the given time instead of now
+        ///
+        /// Accepts a date and time such as "2019-10-17 21:30" or a date relative to today
+        /// such as "yesterday 9pm", "today noon" or "3 days ago 08:00"
+        at: Option<String>,
 
         /// Any tags to associate with the new entry
         tags: Vec<String>,
@@ -60,6 +91,69 @@ enum Jrn {
         #[structopt(short)]
         /// Limit output to most recent n matched entries
         n: Option<usize>,
+
+        #[structopt(short, long = "tag")]
+        /// Only list entries with the given tag, may be repeated
+        ///
+        /// Hierarchical tags match their descendants, work matches work/projectx
+        /// A namespace key matches every tag in the namespace, person matches person:alice
+        tags: Vec<String>,
+
+        #[structopt(short, long)]
+        /// Only list entries whose text matches the given pattern, encrypted entries are decrypted
+        grep: Option<String>,
+
+        #[structopt(long, conflicts_with = "format")]
+        /// Display each entry on a single line, its short id, time, tags and first line
+        oneline: bool,
+
+        #[structopt(long)]
+        /// Display each entry following a template, for example
+        ///     jrn list --format '%h %t [%g]%n%b'
+        ///
+        /// %H id, %h short id, %t time, %l location, %g tags, %f path,
+        /// %s first line, %b body, %n newline, %% a literal %
+        format: Option<String>,
+
+        #[structopt(long)]
+        /// Only display the id, time, location and tags of each entry
+        no_body: bool,
+
+        #[structopt(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Display a single entry
+    Show {
+        #[structopt(default_value = "HEAD")]
+        /// An identifier of the entry to display
+        ///
+        /// Entries are identified by HEAD, HEAD~n, their file name or a prefix of their id
+        entry_descriptor: String,
+
+        #[structopt(short, long, conflicts_with = "body")]
+        /// Only display the id, time, location and tags of the entry
+        metadata: bool,
+
+        #[structopt(short, long)]
+        /// Only display the text of the entry
+        body: bool,
+
+        #[structopt(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Open an existing entry in the JRN_EDITOR
+    ///
+    /// The entry is read again when the editor exits, tags are brought into canonical form
+    ///
+    /// Edit has no --dry-run, the entry only changes by what is saved in the editor
+    Edit {
+        #[structopt(default_value = "HEAD")]
+        /// An identifier of the entry to edit
+        ///
+        /// Entries are identified by HEAD, HEAD~n, their file name or a prefix of their id
+        entry_descriptor: String,
     },
 
     #[structopt(alias = "pt")]
@@ -69,12 +163,36 @@ enum Jrn {
         tag: String,
         /// An identifier of the entry to push to.
         /// Defaults to the last entered entry.
+        ///
+        /// Entries are identified by HEAD, HEAD~n, their file name or a prefix of their id
         entry_descriptor: Option<String>,
+
+        #[structopt(flatten)]
+        confirm: ConfirmArgs,
     },
 
-    /// Modifies tags in the working jrn repository
+    /// Moves an entry to a different time, renaming its file
+    Retime {
+        /// An identifier of the entry to move
+        ///
+        /// Entries are identified by HEAD, HEAD~n, their file name or a prefix of their id
+        entry_descriptor: String,
+
+        /// The new time of the entry, accepts the same forms as new --at
+        when: String,
+
+        #[structopt(flatten)]
+        confirm: ConfirmArgs,
+    },
+
+    /// Lists and modifies tags in the working jrn repository
     ///
-    /// TODO specify tags command
+    /// --list counts the tags matching the filter and --tree shows them by their hierarchy,
+    /// --normalize brings the tags of all entries into canonical form, --migrate renames
+    /// entries named before tags were encoded,
+    /// --delete removes the matching tags from their entries and --new-name renames them
+    ///
+    /// Changes are shown and confirmed before they are made, see --yes and --dry-run
     Tags {
         #[structopt(default_value = ".*")]
         /// Filter to match tags against
@@ -87,89 +205,583 @@ enum Jrn {
         /// Display all tags and the number of times they appear
         list: bool,
 
+        #[structopt(short, long)]
+        /// When listing, also display the entries each tag appears in
+        entries: bool,
+
+        #[structopt(long)]
+        /// Display tags as a tree of their hierarchy, work/projectx is shown below work
+        ///
+        /// Each count includes the entries of all descendant tags
+        tree: bool,
+
+        #[structopt(long)]
+        /// Rename entries so their tags are in canonical form
+        ///
+        /// Tags are trimmed, case folded if the TagCaseFold setting is true
+        /// and mapped through the aliases in .jrnaliases
+        normalize: bool,
+
+        #[structopt(long)]
+        /// Rename entries named before tags were encoded, so their tags keep their meaning
+        ///
+        /// A tag such as c++ written by an older jrn would otherwise be read as c//,
+        /// names that are already encoded such as work+projectx are left alone
+        migrate: bool,
+
         #[structopt(short, long)]
         /// Delete selected tags from all entries
         delete: bool,
 
         #[structopt(long)]
-        /// Rename the selected tag to new_name
+        /// Rename the tag given as the filter to new_name
+        ///
+        /// Descendants are renamed along,
+        /// work/projectx becomes job/projectx when work is renamed to job
         new_name: Option<String>,
+
+        #[structopt(flatten)]
+        confirm: ConfirmArgs,
+
+        #[structopt(flatten)]
+        output: OutputArgs,
     },
 
-    /// Alters or inquires the working configuration
-    ///
-    /// The configuration is pulled from all available git configurations
-    /// and any yet to be defined configuration
+    /// Inquires the working configuration
     ///
-    /// TODO impl config command
+    /// Settings are read from ~/.config/.jrnconfig, ~/.jrnconfig and the .jrnconfig
+    /// at the root of the journal, more local files overriding global ones,
+    /// settings not found in any file take their default
     Config {
         #[structopt(short, long)]
-        /// Lists the mapping of all relevant configuration options to their values
-        ///
-        /// Relevant git config options will be displayed separate from application config options
+        /// Lists every setting and its value, as json objects with --json or --ndjson
         list: bool,
+
+        #[structopt(flatten)]
+        output: OutputArgs,
+    },
+
+    /// List the entries an entry links to
+    ///
+    /// Entries link to each other wiki style by name, time or id
+    ///     [[2019-10-01_0930-work]]  [[2019-10-01_0930]]  [[id:3f2a9c1]]
+    /// Links are kept up to date when the entry they refer to is renamed
+    Links {
+        #[structopt(default_value = "HEAD")]
+        /// An identifier of the entry whose links to list
+        ///
+        /// Entries are identified by HEAD, HEAD~n, their file name or a prefix of their id
+        entry_descriptor: String,
+
+        #[structopt(flatten)]
+        output: OutputArgs,
+    },
+
+    /// List the entries linking to an entry, see jrn links --help
+    Backlinks {
+        #[structopt(default_value = "HEAD")]
+        /// An identifier of the entry to find links to
+        ///
+        /// Entries are identified by HEAD, HEAD~n, their file name or a prefix of their id
+        entry_descriptor: String,
+
+        #[structopt(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Display statistics of the journal
+    ///
+    /// Counts entries per day, week and month, writing streaks, words, tags and locations,
+    /// and draws a heatmap of the last weeks
+    Stats {
+        #[structopt(long)]
+        /// Only count entries written at or after this time, such as 2019-10-01 or "2 weeks ago"
+        since: Option<String>,
+
+        #[structopt(long)]
+        /// Only count entries written before this time
+        until: Option<String>,
+
+        #[structopt(short, long = "tag")]
+        /// Only count entries with the given tag, may be repeated
+        tags: Vec<String>,
+
+        #[structopt(long, default_value = "10")]
+        /// The number of most used tags to display
+        top: usize,
+
+        #[structopt(long, default_value = "26")]
+        /// The number of weeks to chart and draw in the heatmap
+        weeks: usize,
+
+        #[structopt(flatten)]
+        output: OutputArgs,
+    },
+
+    /// Display a calendar of the days entries were written
+    ///
+    /// Days with entries are highlighted and show their number of entries
+    Cal {
+        /// The month to display, such as 2019-10, 10 or oct, this month if not given
+        month: Option<String>,
+
+        #[structopt(short, long = "tag")]
+        /// Only count entries with the given tag, may be repeated
+        tags: Vec<String>,
+
+        #[structopt(long, conflicts_with = "month")]
+        /// List the entries written on this day instead, such as 2019-10-01 or yesterday
+        day: Option<String>,
+    },
+
+    /// Attach a file to an entry
+    ///
+    /// The file is copied next to the entry and linked at the end of its text
+    Attach {
+        /// An identifier of the entry to attach to
+        ///
+        /// Entries are identified by HEAD, HEAD~n, their file name or a prefix of their id
+        entry_descriptor: String,
+
+        #[structopt(parse(from_os_str))]
+        /// The file to attach
+        file: PathBuf,
+
+        #[structopt(flatten)]
+        confirm: ConfirmArgs,
+    },
+
+    /// Browse entries in an interactive terminal interface
+    ///
+    /// Entries are listed newest first beside a preview of the selected one.
+    /// Press / to filter as you type, words starting with # match tags and
+    /// other words must appear in the name or text of the entry
+    ///
+    /// e edits the entry, t pushes a tag, T removes one and d deletes the entry
+    /// after asking, q quits
+    Browse,
+
+    /// Undo the last commands that created, renamed or removed entries
+    ///
+    /// new, retime, push-tag, remove-tag, tags --new-name, tags --delete, tags --normalize,
+    /// tags --migrate, edit, rm, import and trash-restore are recorded in .jrn/undo.log
+    /// Created entries are moved to the trash, renamed entries get their old name back
+    /// and removed entries are restored from the trash
+    Undo {
+        #[structopt(default_value = "1")]
+        /// The number of commands to undo
+        n: usize,
+
+        #[structopt(flatten)]
+        confirm: ConfirmArgs,
     },
 
     #[structopt(alias = "rm")]
     /// Remove entries or tags
+    ///
+    /// Removed entries are moved to .jrn/trash, jrn undo or jrn trash restore puts them back
     Remove {
         /// The hash of the entry object to be removed
         ///
-        /// if given the literal 'HEAD' will delete only the most recent entry
-        entry_hash: Option<String>
+        /// if given the literal 'HEAD' will delete only the most recent entry,
+        /// HEAD~n, a file name or a unique prefix of the hash may also be given
+        entry_hash: Option<String>,
+
+        #[structopt(flatten)]
+        confirm: ConfirmArgs,
+    },
+
+    /// Inspect, restore or empty the trash of removed entries in .jrn/trash
+    Trash(TrashCommand),
+
+    /// Write the journal out for reading elsewhere
+    Export(ExportFormat),
+
+    /// Import entries from another journal
+    ///
+    /// Entries at a time that already holds an entry are skipped,
+    /// entries written in the same minute as an earlier one are moved on to the next free minute
+    Import {
+        #[structopt(possible_values = ImportFormat::names(), case_insensitive = true)]
+        /// The format to import
+        ///
+        /// jrnl    a jrnl.sh plain text journal, @words become tags
+        /// dayone  a Day One JSON export
+        /// md      a folder of Markdown files named by date, 2019-10-01.md
+        format: ImportFormat,
+
+        #[structopt(parse(from_os_str))]
+        /// The journal file or folder to import
+        path: PathBuf,
+
+        #[structopt(short, long = "tag")]
+        /// Add the given tag to every imported entry, may be repeated
+        tags: Vec<String>,
+
+        #[structopt(short = "n", long)]
+        /// Only list the entries that would be imported
+        dry_run: bool,
+    },
+
+    /// Generate an age identity for encrypted entries
+    ///
+    /// Entries are encrypted when the Encrypt setting is true,
+    /// the identity is written to the IdentityFile setting unless a path is given.
+    /// Keep a copy of it somewhere safe, encrypted entries can not be read without it
+    Keygen {
+        #[structopt(parse(from_os_str))]
+        /// Where to write the identity
+        path: Option<PathBuf>,
+    },
+
+    /// Generate a shell completion script
+    ///
+    /// The script completes tags and entry ids from the current repository,
+    /// for example with bash
+    ///     jrn completions bash > /etc/bash_completion.d/jrn
+    Completions {
+        #[structopt(possible_values = &Shell::variants(), case_insensitive = true)]
+        /// The shell to generate the script for
+        shell: Shell,
+    },
+
+    #[structopt(name = "__complete", rename_all = "verbatim", setting = AppSettings::Hidden)]
+    /// Lists candidates for dynamic completion, used by the completion scripts
+    Complete {
+        #[structopt(possible_values = &["tags", "entries"])]
+        /// The kind of candidates to list
+        kind: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum ExportFormat {
+    /// Render every entry to a static html site, viewable offline
+    ///
+    /// The site has an index of every entry, a permalink page per entry id,
+    /// a page per tag and an archive per month
+    Html {
+        #[structopt(parse(from_os_str))]
+        /// The directory to write the site into, created if missing
+        out_dir: PathBuf,
+    },
+
+    /// Concatenate entries into one Markdown document with a table of contents
+    ///
+    /// Entries are written oldest first, each under a heading with its date, location and tags.
+    /// The output is plain Markdown, for example
+    ///     jrn export md --since "2019-10-01" --tag work | pandoc -o review.pdf
+    Md {
+        #[structopt(long)]
+        /// Only include entries written at or after this time, such as 2019-10-01 or "2 weeks ago"
+        since: Option<String>,
+
+        #[structopt(long)]
+        /// Only include entries written before this time
+        until: Option<String>,
+
+        #[structopt(short, long = "tag")]
+        /// Only include entries with the given tag, may be repeated
+        tags: Vec<String>,
+
+        #[structopt(short, long, parse(from_os_str))]
+        /// Write to this file instead of std::out
+        output: Option<PathBuf>,
     },
 }
 
+#[derive(Debug, StructOpt)]
+enum TrashCommand {
+    /// List the entries in the trash with their id, when they were removed and their original path
+    List,
+
+    /// Put an entry in the trash back where it was removed from
+    Restore {
+        /// The id of the entry in the trash, or a unique prefix of it, as shown by jrn trash list
+        id: String,
+
+        #[structopt(flatten)]
+        confirm: ConfirmArgs,
+    },
+
+    /// Permanently delete the entries in the trash
+    Empty {
+        #[structopt(long)]
+        /// Only delete entries removed longer ago than this age, such as 30d, 2w or 12h
+        older_than: Option<String>,
+
+        #[structopt(flatten)]
+        confirm: ConfirmArgs,
+    },
+}
+
+/// How commands that change entries go ahead, see [Confirm]
+///
+/// Confirmation is asked for on a terminal, without one --yes is needed
+#[derive(Debug, StructOpt)]
+struct ConfirmArgs {
+    #[structopt(short, long)]
+    /// Go ahead without asking for confirmation
+    yes: bool,
+
+    #[structopt(long)]
+    /// Only show what would be changed
+    dry_run: bool,
+}
+
+impl ConfirmArgs {
+    fn confirm(&self) -> Confirm {
+        if self.dry_run {
+            Confirm::DryRun
+        } else if self.yes {
+            Confirm::Yes
+        } else {
+            Confirm::Ask
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct OutputArgs {
+    #[structopt(long, conflicts_with = "ndjson")]
+    /// Write the results as json
+    ///
+    /// Entries, tags and settings are written as json objects with a stable schema
+    json: bool,
+
+    #[structopt(long)]
+    /// Write the results as newline delimited json, one value per line
+    ndjson: bool,
+}
+
+impl OutputArgs {
+    fn output(&self) -> Output {
+        if self.json {
+            Output::Json
+        } else if self.ndjson {
+            Output::Ndjson
+        } else {
+            Output::Text
+        }
+    }
+}
+
 impl Jrn {
     // app builder in which to change apply any [clap::AppSettings]
     // using this pattern allows a shorter structopt derive
-    fn build_app() -> Self {
+    fn build_app() -> (Self, Option<ColorChoice>) {
         let clap_app = Jrn::clap()
             .setting(AppSettings::VersionlessSubcommands)
-            .setting(AppSettings::DisableVersion);
-        Jrn::from_clap(&clap_app.get_matches())
+            .setting(AppSettings::DisableVersion)
+            .arg(Arg::with_name("color")
+                .long("color")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["auto", "always", "never"])
+                .help("When to colour output, overrides the Color setting"));
+        let matches = clap_app.get_matches();
+        let color = matches.value_of("color").and_then(|c| c.parse().ok());
+        (Jrn::from_clap(&matches), color)
     }
 
-    fn start_loop(self, repo: JrnRepo) {
-        self.match_on_command(repo).expect("Debug");
+    fn start_loop(self, color: Option<ColorChoice>) {
+        if let Err(e) = self.run(color) {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // commands that do not read the journal run without opening the repository
+    fn run(self, color: Option<ColorChoice>) -> Result<(), JrnError> {
+        match self {
+            Jrn::Completions { shell } => {
+                let stdout = std::io::stdout();
+                completions::write_script(Jrn::clap(), shell, &mut stdout.lock())?;
+                Ok(())
+            }
+            // completing tags only reads file names, it runs on every TAB
+            Jrn::Complete { kind } if kind == "tags" => {
+                let (cfg, ignore, aliases) = configuration();
+                let root = std::env::current_dir()?;
+                for tag in JrnRepo::tags_below(&root, &cfg, &ignore, &aliases) {
+                    println!("{}", tag.1);
+                }
+                Ok(())
+            }
+            command => command.match_on_command(open_repo(color)?),
+        }
     }
 
     fn match_on_command(self, mut repo: JrnRepo) -> Result<(), JrnError>{
         use self::Jrn::*;
         match self {
-            New { skip_edit, location, tags } => {
-                repo.create_entry(tags, location, skip_edit)?;
+            New { skip_edit, location, at, tags } => {
+                repo.create_entry(tags, location, at.as_deref(), skip_edit)?;
             }
-            List { pattern, n } => {
-                repo.list_entries(pattern.as_ref(), n)?;
+            List { pattern, n, tags, grep, oneline, format, no_body, output } => {
+                let template = match format {
+                    Some(format) => Some(Template::parse(&format)),
+                    None if oneline => Some(Template::oneline()),
+                    None => None,
+                };
+                let query = EntryQuery {
+                    pattern: &pattern,
+                    grep: grep.as_deref(),
+                    tags: &tags,
+                    ..EntryQuery::default()
+                };
+                let matched = repo.select(&query)?;
+                let skipped = n.map_or(0, |n| matched.len().saturating_sub(n));
+                let matched = matched[skipped..].iter().copied();
+                print::entries(&repo, matched, template.as_ref(), !no_body, output.output())?;
             }
-            PushTag { tag, entry_descriptor} => {
-                repo.push_tag(&tag, entry_descriptor);
+            Show { entry_descriptor, metadata, body, output } => {
+                let entry = repo.entry(&entry_descriptor)?;
+                print::entry(&repo, entry, !body, !metadata, output.output())?;
             }
-            Tags { pattern, list, delete, new_name } => {
+            Edit { entry_descriptor } => {
+                repo.edit_entry(&entry_descriptor)?;
+            }
+            PushTag { tag, entry_descriptor, confirm } => {
+                let confirm = confirm.confirm();
+                repo.push_tag(&tag, entry_descriptor.as_deref(), |plan| confirm.confirm(plan))?;
+            }
+            Retime { entry_descriptor, when, confirm } => {
+                let confirm = confirm.confirm();
+                repo.retime(&entry_descriptor, &when, |plan| confirm.confirm(plan))?;
+            }
+            Tags {
+                pattern, list, entries, tree, normalize, migrate, delete, new_name, confirm, output,
+            } => {
+                let confirm = confirm.confirm();
+                if migrate {
+                    repo.migrate_tags(|plan| confirm.confirm(plan))?;
+                }
+                if normalize {
+                    repo.normalize_tags(|plan| confirm.confirm(plan))?;
+                }
+                if tree {
+                    print::tag_tree(&repo, &pattern)?;
+                } else if list {
+                    print::tags(&repo, &pattern, entries, output.output())?;
+                }
+                if let Some(new_name) = new_name {
+                    repo.rename_tag(&pattern, &new_name, |plan| confirm.confirm(plan))?;
+                }
+                if delete {
+                    repo.delete_tags(&pattern, |plan| confirm.confirm(plan))?;
+                }
+            }
+            Config { list, output } => {
                 if list {
-                    repo.list_tags(&pattern)?;
+                    print::config(&repo, output.output())?;
+                }
+            }
+            Links { entry_descriptor, output } => {
+                let linked = repo.links(&entry_descriptor)?;
+                print::entries(&repo, linked, Some(&Template::oneline()), false, output.output())?;
+            }
+            Backlinks { entry_descriptor, output } => {
+                let linking = repo.backlinks(&entry_descriptor)?;
+                print::entries(&repo, linking, Some(&Template::oneline()), false, output.output())?;
+            }
+            Stats { since, until, tags, top, weeks, output } => {
+                let query = EntryQuery {
+                    since: since.as_deref(),
+                    until: until.as_deref(),
+                    tags: &tags,
+                    ..EntryQuery::default()
+                };
+                print::stats(&repo, &query, top, weeks, output.output())?;
+            }
+            Cal { month, tags, day } => {
+                let query = EntryQuery { tags: &tags, ..EntryQuery::default() };
+                match day {
+                    Some(day) => {
+                        let (entries, oneline) = (repo.day(&day, &query)?, Template::oneline());
+                        print::entries(&repo, entries, Some(&oneline), false, Output::Text)?
+                    }
+                    None => print::calendar(&repo, &repo.calendar(month.as_deref(), &query)?)?,
                 }
-                //TODO implement tags command
             }
-            Config { .. } => {
-                //TODO implement config command
+            Attach { entry_descriptor, file, confirm } => {
+                let confirm = confirm.confirm();
+                let copy = repo.attach(&entry_descriptor, &file, |plan| confirm.confirm(plan))?;
+                if let Some(copy) = copy {
+                    println!("{}", copy.display());
+                }
+            }
+            Browse => {
+                browse::run(&mut repo)?;
+            }
+            Undo { n, confirm } => {
+                let confirm = confirm.confirm();
+                let mut asked = false;
+                repo.undo(n, |plan| {
+                    asked = true;
+                    confirm.confirm(plan)
+                })?;
+                if !asked {
+                    println!("nothing to undo");
+                }
             }
-            Remove { entry_hash } => {
+            Remove { entry_hash, confirm } => {
                 match entry_hash {
-                    Some(s) => { 
-                        if &s == "HEAD" {
-                            repo.remove_latest()?;
-                        } else {
-                            log::info!("TODO impl remove hash");
-                            log::info!("Found hash {}", &s);
-                        }
+                    Some(s) => {
+                        let confirm = confirm.confirm();
+                        repo.remove_entry(&s, |plan| confirm.confirm(plan))?;
                     },
                     None => { 
                         log::info!("TODO display jrn-remove help");
                     }
                 }
             }
+            Trash(TrashCommand::List) => {
+                print::trash(&repo)?;
+            }
+            Trash(TrashCommand::Restore { id, confirm }) => {
+                let confirm = confirm.confirm();
+                repo.restore_trash(&id, |plan| confirm.confirm(plan))?;
+            }
+            Trash(TrashCommand::Empty { older_than, confirm }) => {
+                let confirm = confirm.confirm();
+                repo.empty_trash(older_than.as_deref(), |plan| confirm.confirm(plan))?;
+            }
+            Export(ExportFormat::Html { out_dir }) => {
+                repo.export_html(&out_dir)?;
+            }
+            Export(ExportFormat::Md { since, until, tags, output }) => {
+                let query = EntryQuery {
+                    since: since.as_deref(),
+                    until: until.as_deref(),
+                    tags: &tags,
+                    ..EntryQuery::default()
+                };
+                match output {
+                    Some(path) => {
+                        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                        repo.export_markdown(&query, &mut file)?;
+                        file.flush()?;
+                    }
+                    None => repo.export_markdown(&query, &mut std::io::stdout().lock())?,
+                }
+            }
+            Import { format, path, tags, dry_run } => {
+                let imported = repo.import(format, &path, &tags, dry_run)?;
+                print::imported(&imported, dry_run)?;
+            }
+            Keygen { path } => {
+                let (path, public) = repo.keygen(path.as_deref())?;
+                println!("identity written to {}", path.display());
+                println!("public key: {}", public);
+            }
+            Completions { .. } => unreachable!("completions run without a repository"),
+            Complete { .. } => {
+                println!("HEAD");
+                for id in repo.recent_entry_ids(completions::RECENT_ENTRIES) {
+                    println!("{}", id);
+                }
+            }
         }
         Ok(())
     }
@@ -0,0 +1,92 @@
+//! Pipes long output through a pager, as git does
+//!
+//! The pager is taken from $PAGER, falling back to less -R.
+//! Output is only paged when stdout is a terminal
+
+use std::io::{self, IsTerminal, Stdout, Write};
+use std::process::{Child, Command, Stdio};
+
+static DEFAULT_PAGER: &str = "less -R";
+/// passed to less unless the user has their own LESS options,
+/// quits if the output fits on one screen and keeps colours
+static DEFAULT_LESS: &str = "FRX";
+
+/// Standard output, or the input of a pager
+pub(crate) enum PagedStdout {
+    Pager(Child),
+    Stdout(Stdout),
+}
+
+impl PagedStdout {
+    /// Starts the pager if stdout is a terminal,
+    /// writes directly to stdout otherwise or if the pager can not be started
+    pub fn paged() -> Self {
+        if !io::stdout().is_terminal() {
+            return PagedStdout::Stdout(io::stdout());
+        }
+        match spawn_pager() {
+            Some(child) => PagedStdout::Pager(child),
+            None => PagedStdout::Stdout(io::stdout()),
+        }
+    }
+}
+
+fn spawn_pager() -> Option<Child> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| String::from(DEFAULT_PAGER));
+    let mut words = pager.split_whitespace();
+    let program = words.next()?;
+    if program == "cat" {
+        return None;
+    }
+
+    let mut cmd = Command::new(program);
+    cmd.args(words).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        cmd.env("LESS", DEFAULT_LESS);
+    }
+    match cmd.spawn() {
+        Ok(child) => Some(child),
+        Err(e) => {
+            log::warn!("Can not start pager \"{}\": {}", pager, e);
+            None
+        }
+    }
+}
+
+impl Write for PagedStdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            PagedStdout::Pager(child) => match child.stdin.as_mut().map(|stdin| stdin.write(buf)) {
+                // the pager was closed before reading everything
+                Some(Err(ref e)) if e.kind() == io::ErrorKind::BrokenPipe => Ok(buf.len()),
+                Some(result) => result,
+                None => Ok(buf.len()),
+            },
+            PagedStdout::Stdout(stdout) => stdout.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            PagedStdout::Pager(child) => match child.stdin.as_mut().map(Write::flush) {
+                Some(Err(ref e)) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                Some(result) => result,
+                None => Ok(()),
+            },
+            PagedStdout::Stdout(stdout) => stdout.flush(),
+        }
+    }
+}
+
+impl Drop for PagedStdout {
+    /// Waits for the user to close the pager
+    fn drop(&mut self) {
+        if let PagedStdout::Pager(child) = self {
+            // closing stdin lets the pager know the output is complete
+            drop(child.stdin.take());
+            if let Err(e) = child.wait() {
+                log::warn!("Pager failed: {}", e);
+            }
+        }
+    }
+}
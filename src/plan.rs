@@ -0,0 +1,74 @@
+//! The changes a command is about to make, shown before they are made
+//!
+//! Commands that change entries first collect what they will do into a [Plan]
+//! and hand it to a confirmation callback, which decides whether to go ahead.
+//! Each change is a line such as
+//!
+//! ```text
+//!     remove   2019-10-01_0930-work
+//! ```
+//!
+//! An empty plan is never handed over, there is nothing to confirm
+
+use super::JrnError;
+
+/// The changes a command is about to make, one line each
+#[derive(Debug, PartialEq)]
+pub struct Plan {
+    /// the name of the command, such as rm
+    command: String,
+    changes: Vec<String>,
+    /// false if the plan is small enough to go ahead without asking
+    ask: bool,
+}
+
+impl Plan {
+    pub fn new(command: &str) -> Self {
+        Plan {
+            command: String::from(command),
+            changes: Vec::new(),
+            ask: true,
+        }
+    }
+
+    pub fn push(&mut self, change: String) {
+        self.changes.push(change);
+    }
+
+    /// Only asks if ask is true, see [Plan::asks]
+    pub(crate) fn asking_if(mut self, ask: bool) -> Self {
+        self.ask = ask;
+        self
+    }
+
+    /// Hands the plan to confirm unless it is empty, returning true if the changes should be made
+    pub(crate) fn confirmed_by<F>(&self, confirm: F) -> Result<bool, JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        Ok(!self.changes.is_empty() && confirm(self)?)
+    }
+
+    /// The name of the command, such as rm
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// The changes to be made, one line each
+    pub fn changes(&self) -> &[String] {
+        &self.changes
+    }
+
+    /// false if the command would go ahead without asking, such as renaming a single entry
+    pub fn asks(&self) -> bool {
+        self.ask
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
@@ -0,0 +1,193 @@
+//! Writes what the repo returns to std::out, as text or json
+//!
+//! Entries and statistics are paged, see [PagedStdout]
+
+use std::io::{self, IsTerminal, Write};
+
+use chrono::Local;
+use jrn::json::{self, EntryJson, SettingJson, TagJson};
+use jrn::{paint, Calendar, EntryQuery, Imported, JrnEntry, JrnError, JrnRepo, Output, Style};
+use jrn::{TagNode, Template};
+
+use crate::pager::PagedStdout;
+
+/// true if output to std::out should be coloured, see [jrn::ColorChoice::enabled]
+pub fn color(repo: &JrnRepo) -> bool {
+    repo.get_color().enabled(io::stdout().is_terminal())
+}
+
+/// Writes entries as text following template, or as json
+pub fn entries<'a>(
+    repo: &JrnRepo,
+    entries: impl IntoIterator<Item = &'a JrnEntry>,
+    template: Option<&Template>,
+    body: bool,
+    output: Output,
+) -> Result<(), JrnError> {
+    let entries = entries.into_iter();
+    if output != Output::Text {
+        let entries = entries
+            .map(|entry| EntryJson::new(entry, body, repo))
+            .collect::<Result<Vec<_>, _>>()?;
+        return json::write_all(&mut io::stdout().lock(), output, entries);
+    }
+
+    let mut pager = PagedStdout::paged();
+    let color = color(repo);
+    for entry in entries {
+        writeln!(pager, "{}", entry.display(repo).body(body).template(template).color(color))?;
+    }
+    Ok(())
+}
+
+/// Writes a single entry, either its metadata or its body may be left out
+pub fn entry(
+    repo: &JrnRepo,
+    entry: &JrnEntry,
+    metadata: bool,
+    body: bool,
+    output: Output,
+) -> Result<(), JrnError> {
+    if output != Output::Text {
+        let entry = EntryJson::new(entry, body, repo)?;
+        return json::write_one(&mut io::stdout().lock(), output, &entry);
+    }
+
+    let mut pager = PagedStdout::paged();
+    write!(pager, "{}", entry.display(repo).metadata(metadata).body(body).color(color(repo)))?;
+    Ok(())
+}
+
+/// Writes the tags matching pattern and their counts,
+/// optionally followed by the entries carrying each tag
+pub fn tags(
+    repo: &JrnRepo,
+    pattern: &str,
+    show_entries: bool,
+    output: Output,
+) -> Result<(), JrnError> {
+    let tags = repo.tags_matching(pattern)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if output != Output::Text {
+        let tags = tags.iter().map(|tag| TagJson {
+            tag,
+            entries: match show_entries {
+                true => Some(repo.tag_entries(&tag.1)),
+                false => None,
+            },
+        });
+        return json::write_all(&mut out, output, tags);
+    }
+
+    for tag in &tags {
+        writeln!(out, "{}: {}", tag.1, tag.0)?;
+        if show_entries {
+            for path in repo.tag_entries(&tag.1) {
+                writeln!(out, "    {}", path.display())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the tags matching pattern as a tree, each counted together with its descendants
+pub fn tag_tree(repo: &JrnRepo, pattern: &str) -> Result<(), JrnError> {
+    fn write_node<W: Write>(out: &mut W, node: &TagNode, depth: usize) -> io::Result<()> {
+        writeln!(out, "{}{}: {}", "    ".repeat(depth), node.name, node.total)?;
+        for child in &node.children {
+            write_node(out, child, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for node in repo.tag_tree(pattern)? {
+        write_node(&mut out, &node, 0)?;
+    }
+    Ok(())
+}
+
+/// Writes every setting and its value
+pub fn config(repo: &JrnRepo, output: Output) -> Result<(), JrnError> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if output != Output::Text {
+        let settings = repo.iter().map(|(setting, value)| SettingJson { setting, value });
+        return json::write_all(&mut out, output, settings);
+    }
+
+    for (setting, value) in repo.iter() {
+        writeln!(out, "{:?} = {}", setting, value)?;
+    }
+    Ok(())
+}
+
+/// Writes statistics of the entries matching query, see [jrn::Stats]
+///
+/// top limits the tags shown, weeks the weeks charted and shown in the heatmap,
+/// which ends today or on the last day before until
+pub fn stats(
+    repo: &JrnRepo,
+    query: &EntryQuery,
+    top: usize,
+    weeks: usize,
+    output: Output,
+) -> Result<(), JrnError> {
+    let stats = repo.stats(query, top)?;
+    if output != Output::Text {
+        return json::write_one(&mut io::stdout().lock(), output, &stats);
+    }
+
+    let end = query.last_day(Local::now().naive_local().date())?;
+    let mut text = String::new();
+    stats.write_text(&mut text, end, weeks, color(repo)).expect("writing to a String");
+    let mut pager = PagedStdout::paged();
+    write!(pager, "{}", text)?;
+    Ok(())
+}
+
+/// Writes the grid of a month
+pub fn calendar(repo: &JrnRepo, calendar: &Calendar) -> Result<(), JrnError> {
+    let mut text = String::new();
+    calendar.write(&mut text, color(repo)).expect("writing to a String");
+    print!("{}", text);
+    Ok(())
+}
+
+/// Writes the id, removal time and original path of each entry in the trash
+pub fn trash(repo: &JrnRepo) -> Result<(), JrnError> {
+    let color = color(repo);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for item in repo.trash()? {
+        writeln!(
+            out,
+            "{}  {}  {}",
+            paint(&item.id, Style::Id, color),
+            paint(item.time.format("%Y-%m-%d %H:%M"), Style::Time, color),
+            item.path.display(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes what became of each imported record, followed by a summary
+pub fn imported(imported: &[Imported], dry_run: bool) -> Result<(), JrnError> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for record in imported {
+        let action = match (record.skipped, dry_run) {
+            (true, _) => "skip",
+            (false, true) => "would import",
+            (false, false) => "import",
+        };
+        writeln!(out, "{:<12}  {}", action, record.name)?;
+    }
+
+    let skipped = imported.iter().filter(|record| record.skipped).count();
+    let verb = if dry_run { "would be imported" } else { "imported" };
+    writeln!(out, "{} {}, {} skipped", imported.len() - skipped, verb, skipped)?;
+    Ok(())
+}
@@ -1,18 +1,62 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashSet};
 use std::env;
 use std::fs::{self, OpenOptions};
-use std::io::{self, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use super::*;
 use std::ops::Deref;
+use chrono::{Local, NaiveDate};
 use regex::Regex;
+use crate::link::{self, LinkIndex, Names, Target};
+use crate::trash;
+use crate::undo::{Operation, Record, UndoLog};
+
+/// Selects entries by their name, text and tags
+#[derive(Debug, Default)]
+pub struct EntryQuery<'a> {
+    /// a regex the name of the entry must match, an empty pattern matches every entry
+    pub pattern: &'a str,
+    /// a regex the text of the entry must match
+    pub grep: Option<&'a str>,
+    /// tags the entry must carry, see [tag::matches]
+    pub tags: &'a [String],
+    /// the entry must be written at or after this time, accepts the same times as [JrnRepo::retime]
+    pub since: Option<&'a str>,
+    /// the entry must be written before this time
+    pub until: Option<&'a str>,
+}
+
+impl EntryQuery<'_> {
+    /// The last day entries matching this query can be written on, no later than today
+    pub fn last_day(&self, today: NaiveDate) -> Result<NaiveDate, JrnError> {
+        Ok(match self.until {
+            Some(until) => {
+                let until = parse_when(until)?.date();
+                until.pred_opt().unwrap_or(NaiveDate::MIN).min(today)
+            }
+            None => today,
+        })
+    }
+}
+
+/// An entry renamed by a command, see [JrnRepo::finish]
+struct Renamed {
+    /// the names the entry was known by before and after
+    names: (Names, Names),
+    operation: Operation,
+}
 
 /// in memory knowledge of JrnRepo on disk
+///
+/// A repo only returns what it finds, writing to std::out is left to its caller.
+/// Commands that change several entries hand a [Plan] of their changes to a confirmation
+/// callback first, and only go ahead if it returns true
 pub struct JrnRepo {
     pub root_path: PathBuf,
     config: Settings,
     ignore: IgnorePatterns,
+    aliases: TagAliases,
     /// entries sorted by creation time
     entries: Vec<JrnEntry>,
     tags: TagContainer,
@@ -29,14 +73,40 @@ impl JrnRepo {
     ///
     /// returning Err if unable to write new entries
     /// will not return Err if unable to read files in dir
-    pub fn init(config: Settings, ignore: IgnorePatterns) -> Result<Self, JrnError> {
+    pub fn init(
+        config: Settings,
+        ignore: IgnorePatterns,
+        aliases: TagAliases,
+    ) -> Result<Self, JrnError> {
         let root_path: PathBuf = env::current_dir()
             .expect("jrn needs access to the repository root");
+        JrnRepo::init_at(root_path, config, ignore, aliases)
+    }
+
+    /// Opens the repo rooted at root, with the configuration found for it
+    ///
+    /// root/.jrnconfig, root/.jrnignore and root/.jrnaliases are read
+    /// in place of the ones in the current working dir, see [Settings::find_or_default]
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<Self, JrnError> {
+        let root = root.as_ref();
+        let config = Settings::find_or_default_in(root);
+        let ignore = IgnorePatterns::find_or_default_in(root);
+        let aliases = TagAliases::find_or_default_in(root, &config);
+        JrnRepo::init_at(root.to_path_buf(), config, ignore, aliases)
+    }
 
+    /// Initializes the repo rooted at root_path, see [JrnRepo::init]
+    pub fn init_at(
+        root_path: PathBuf,
+        config: Settings,
+        ignore: IgnorePatterns,
+        aliases: TagAliases,
+    ) -> Result<Self, JrnError> {
         let mut repo = JrnRepo {
             root_path,
             config,
             ignore,
+            aliases,
             entries: Vec::new(),
             tags: TagContainer::new(),
         };
@@ -45,136 +115,1024 @@ impl JrnRepo {
     }
 
     /// Tries to create a new entry in this repo
+    ///
+    /// The entry is created at the current time unless a time is given, see [JrnRepo::retime]
+    ///
+    /// With Encrypt set the entry is written in a private scratch file and encrypted once
+    /// the editor exits, see [JrnRepo::edit_entry]
     pub fn create_entry(
         &mut self,
         tags: Vec<String>,
         location: Option<String>,
+        at: Option<&str>,
         skip_edit: bool,
     ) -> Result<(), JrnError> {
-        let entry = JrnEntry::new(&self, None, tags, location);
+        let creation_time = at.map(parse_when).transpose()?;
+        let mut entry = JrnEntry::new(self, creation_time, tags, location);
+        if entry.is_obfuscated(&self.config) {
+            let name = entry.file_name(&self.config);
+            let existing = self.entries
+                .iter()
+                .find(|e| e.is_obfuscated(&self.config) && e.file_name(&self.config) == name);
+            if let Some(existing) = existing {
+                entry.file_path = existing.file_path.clone();
+            }
+        }
         let path = &entry.file_path;
 
-        if !skip_edit {
-            self.config.launch_editor(Some(&path))?;
+        if entry.is_encrypted() {
+            // fail before the editor opens rather than losing what was written
+            self.config.keys()?;
+            let existing = match path.exists() {
+                true => Some(entry.body(&self.config)?),
+                false => None,
+            };
+            let initial = existing.clone().unwrap_or_default();
+            let text = match skip_edit {
+                true => initial,
+                false => self.edit_text(&initial)?,
+            };
+            if existing.as_deref() != Some(text.as_str()) {
+                entry.write_body(&self.config, &text)?;
+            }
+        } else if !skip_edit {
+            self.config.launch_editor(Some(path))?;
         } else {
-            let mut file = OpenOptions::new().write(true).create(true).open(path)?;
+            let mut file = OpenOptions::new().write(true).create(true).truncate(false).open(path)?;
             //TODO test if line is needed
             file.write_all(&[])?;
         }
 
-        self.entries.push(entry);
+        // an entry already at this time and location was only reopened
+        if !self.entries.iter().any(|e| e.file_path == entry.file_path) {
+            if entry.file_path.exists() {
+                // the location is recorded once the editor is done with the file
+                if !entry.is_encrypted() && entry.location.as_option().is_some() {
+                    entry.write_body(&self.config, &entry.body(&self.config)?)?;
+                }
+                let path = relative(&self.root_path, &entry.file_path);
+                self.record("new", vec![Operation::Create { path }]);
+            }
+            self.entries.push(entry);
+            self.track(self.entries.len() - 1);
+            self.entries.sort();
+        }
         Ok(())
     }
 
+    /// Moves the entry found by descriptor to the time when, renaming its file
+    ///
+    /// when accepts dates and times such as 2019-10-17 21:30 or yesterday 9pm.
+    /// The rename goes ahead without asking, see [Plan::asks]
+    pub fn retime<F>(&mut self, descriptor: &str, when: &str, confirm: F) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let creation_time = parse_when(when)?;
+        let index = self.find_entry(descriptor)?;
+        let entry = &self.entries[index];
+        let mut plan = Plan::new("retime");
+        let new_name = entry.file_name_at(creation_time, &self.config);
+        if new_name != entry.file_name(&self.config) {
+            plan.push(format!("{} -> {}", entry.file_name(&self.config), new_name));
+        }
+        if !plan.asking_if(false).confirmed_by(confirm)? {
+            return Ok(());
+        }
+
+        let renamed = self.change_entry(index, |entry, config, _| {
+            entry.retime(creation_time, config)
+        })?;
+        self.entries.sort();
+        self.finish("retime", renamed.into_iter().collect())
+    }
+
+    /// Finds the index of the entry described by descriptor
+    ///
+    /// A descriptor is one of
+    ///     HEAD      the most recent entry
+    ///     HEAD~n    the entry n before the most recent
+    ///     the file name or path of an entry
+    ///     an entry id, or a prefix of one that matches a single entry
+    fn find_entry(&self, descriptor: &str) -> Result<usize, JrnError> {
+        let not_found = || JrnError::EntryNotFound(String::from(descriptor));
+
+        if let Some(back) = descriptor.strip_prefix("HEAD") {
+            let back: usize = match back.strip_prefix('~') {
+                Some(n) => n.parse().map_err(|_| not_found())?,
+                None if back.is_empty() => 0,
+                None => return Err(not_found()),
+            };
+            return self.entries.len().checked_sub(back + 1).ok_or_else(not_found);
+        }
+
+        let path = Path::new(descriptor);
+        if let Some(i) = self.entries.iter().position(|e| {
+            e.file_path == path || e.file_path.file_name() == Some(path.as_os_str())
+        }) {
+            return Ok(i);
+        }
+
+        let mut matched = self.entries.iter().enumerate()
+            .filter(|(_, e)| e.id().starts_with(descriptor));
+        match (matched.next(), matched.next()) {
+            (Some((i, _)), None) => Ok(i),
+            (Some(_), Some(_)) => Err(JrnError::AmbiguousEntry(String::from(descriptor))),
+            _ => Err(not_found()),
+        }
+    }
+
+    /// The entry described by descriptor, see [JrnRepo::find_entry]
+    pub fn entry(&self, descriptor: &str) -> Result<&JrnEntry, JrnError> {
+        Ok(&self.entries[self.find_entry(descriptor)?])
+    }
+
+    /// The rules used to normalize tags in this repo
+    pub fn tag_aliases(&self) -> &TagAliases {
+        &self.aliases
+    }
+
+    /// Rewrites the file path of every entry whose tags are not in their canonical form
+    ///
+    /// Entries are normalized when read, so this only needs to rename files on disk.
+    /// Renaming more than one entry asks for confirmation, see [Plan::asks]
+    pub fn normalize_tags<F>(&mut self, confirm: F) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let mut plan = Plan::new("tags --normalize");
+        for entry in &self.entries {
+            let built = entry.built_file_path(&self.config);
+            if built != entry.file_path {
+                let (from, to) = (self.display_path(&entry.file_path), self.display_path(&built));
+                plan.push(format!("{} -> {}", from, to));
+            }
+        }
+        let ask = plan.len() > 1;
+        if !plan.asking_if(ask).confirmed_by(confirm)? {
+            return Ok(());
+        }
+
+        let mut renamed = Vec::new();
+        let mut result = Ok(());
+        for index in 0..self.entries.len() {
+            match self.rewrite_entry(index) {
+                Ok(r) => renamed.extend(r),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.index_tags();
+        self.finish("tags --normalize", renamed)?;
+        result
+    }
+
+    /// Renames entries named before tags were encoded, so their tags keep their meaning,
+    /// see [tag::is_legacy]
+    ///
+    /// A legacy tag such as c++ would otherwise be read as c//, tags that are already
+    /// encoded are left alone. Renaming more than one entry asks for confirmation, see [Plan::asks]
+    pub fn migrate_tags<F>(&mut self, confirm: F) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let mut plan = Plan::new("tags --migrate");
+        let mut changes = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            if let Some(tags) = entry.legacy_tags(&self.config, &self.aliases) {
+                let new_name = entry.file_name_with_tags(&tags, &self.config);
+                plan.push(format!("{} -> {}", entry.stored_name(&self.config), new_name));
+                changes.push((index, tags));
+            }
+        }
+        let ask = plan.len() > 1;
+        if !plan.asking_if(ask).confirmed_by(confirm)? {
+            return Ok(());
+        }
+        self.set_tags("tags --migrate", changes)
+    }
+
     /// Returns the location to be used by new entries if a location arg was not passed
     /// First returns a location given by the configuration if available
     /// Second returns the previous entries location
     pub fn get_location(&self) -> Option<Location> {
-        if let Some(loc) = self.config.get_location() {
-            Some(loc)
-        } else if let Some(entry) = self.entries.last() {
-            Some(entry.location.clone())
-        } else {
-            None
+        self.config
+            .get_location()
+            .or_else(|| self.entries.last().map(|entry| entry.location.clone()))
+    }
+
+    /// The entries linked from the entry found by descriptor, in the order they are linked,
+    /// see [link]
+    ///
+    /// Links that do not match a single entry are warned about
+    pub fn links(&self, descriptor: &str) -> Result<Vec<&JrnEntry>, JrnError> {
+        let entry = &self.entries[self.find_entry(descriptor)?];
+        let mut linked: Vec<usize> = Vec::new();
+        for link in link::parse(&entry.body(&self.config)?) {
+            match self.resolve(link.target) {
+                Some(index) if !linked.contains(&index) => linked.push(index),
+                Some(_) => {}
+                None => log::warn!("{} does not match a single entry", link.target),
+            }
         }
+        Ok(linked.into_iter().map(|index| &self.entries[index]).collect())
     }
 
-    /// display entries to std::out
-    /// that match the provided string
-    pub fn list_entries(&self, pattern: &str, most_recent: Option<usize>) -> Result<(), JrnError> {
-        let regex = Regex::new(pattern)?;
-        let mut matched: VecDeque<&JrnEntry> = self.entries
-            .iter()
-            .filter(|entry| regex.is_match(entry.file_path_str()))
-            .collect();
+    /// The entries linking to the entry found by descriptor, oldest first, see [link]
+    pub fn backlinks(&self, descriptor: &str) -> Result<Vec<&JrnEntry>, JrnError> {
+        let index = self.find_entry(descriptor)?;
+        let mut linking = Vec::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i == index {
+                continue;
+            }
+            let text = match entry.body(&self.config) {
+                Ok(text) => text,
+                Err(e) => {
+                    log::warn!("Can not search {}: {}", entry.file_path.display(), e);
+                    continue;
+                }
+            };
+            if link::parse(&text).iter().any(|link| self.resolve(link.target) == Some(index)) {
+                linking.push(entry);
+            }
+        }
+        Ok(linking)
+    }
 
-        if let Some(most_recent) = most_recent {
-            let len = matched.len();
-            if most_recent < len {
-                for _ in most_recent..len {
-                    matched.pop_front();
+    /// Finds the entry a link refers to, if exactly one matches
+    ///
+    /// Names are matched against the full name of an entry first, then its time alone
+    fn resolve(&self, target: Target) -> Option<usize> {
+        let matching = |matches: &dyn Fn(&JrnEntry) -> bool| -> Vec<usize> {
+            (0..self.entries.len()).filter(|&i| matches(&self.entries[i])).collect()
+        };
+        let matched = match target {
+            Target::Id(prefix) => matching(&|e| e.id().starts_with(prefix)),
+            Target::Name(name) => {
+                let by_name = matching(&|e| e.file_name(&self.config) == name);
+                match by_name.is_empty() {
+                    true => matching(&|e| link::time_name(e, &self.config) == name),
+                    false => by_name,
                 }
             }
+        };
+        match matched.as_slice() {
+            [index] => Some(*index),
+            _ => None,
         }
+    }
 
-        let stdout = std::io::stdout();
-        let mut handle = stdout.lock();
-        for entry in matched {
-            writeln!(handle, "{}", &entry)?;
+    /// Rewrites the links to the renamed entries, see [link::rewrite]
+    ///
+    /// Entries the [LinkIndex] knows to contain no links are not read.
+    /// Entries that can not be read keep their links, with a warning
+    fn relink(&self, renamed: &[(Names, Names)]) -> Result<(), JrnError> {
+        if renamed.is_empty() {
+            return Ok(());
         }
+        let ids = link::previous_ids(self.entries.iter().map(JrnEntry::id).collect(), renamed);
+        let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut index = LinkIndex::at(&self.root_path);
+        for entry in &self.entries {
+            let path = relative(&self.root_path, &entry.file_path);
+            if let Some(modified) = modified(&entry.file_path) {
+                if !index.may_link(&path, modified) {
+                    continue;
+                }
+            }
+            let text = match entry.body(&self.config) {
+                Ok(text) => text,
+                Err(e) => {
+                    log::warn!("Links in {} were not updated: {}", entry.file_path.display(), e);
+                    continue;
+                }
+            };
+            if let Some(text) = link::rewrite(&text, renamed, &ids) {
+                entry.write_body(&self.config, &text)?;
+            }
+            if let Some(modified) = modified(&entry.file_path) {
+                index.insert(path, modified, text.contains("[["));
+            }
+        }
+        let paths: HashSet<PathBuf> =
+            self.entries.iter().map(|e| relative(&self.root_path, &e.file_path)).collect();
+        index.retain(|path| paths.contains(path));
+        index.save();
         Ok(())
     }
 
-    pub fn list_tags(&self, pattern: &str) -> Result<(), JrnError> {
-        let regex = Regex::new(pattern)?;
-        let tags = self.tags.sorted();
-        for tag in tags {
-            if regex.is_match(&tag.1) {
-                println!("{}: {}", tag.1, tag.0);
+    /// Opens the entry found by descriptor in the editor, see [JrnRepo::find_entry]
+    ///
+    /// The entry is read again once the editor exits, so its tags are
+    /// brought into canonical form and a deleted file is forgotten
+    ///
+    /// Encrypted entries are decrypted to a private scratch file for the editor,
+    /// and encrypted again if it was changed, see [crypt::Scratch]
+    pub fn edit_entry(&mut self, descriptor: &str) -> Result<(), JrnError> {
+        let index = self.find_entry(descriptor)?;
+        let entry = &self.entries[index];
+        if entry.is_encrypted() {
+            let body = entry.body(&self.config)?;
+            let text = self.edit_text(&body)?;
+            if text != body {
+                entry.write_body(&self.config, &text)?;
             }
+        } else {
+            self.config.launch_editor(Some(&entry.file_path))?;
         }
+        let renamed = self.reload_entry(index)?;
+        self.finish("edit", renamed.into_iter().collect())
+    }
+
+    /// The entries matching query, oldest first
+    ///
+    /// The names and bodies of encrypted entries are decrypted to be matched
+    pub fn select(&self, query: &EntryQuery) -> Result<Vec<&JrnEntry>, JrnError> {
+        let regex = Regex::new(query.pattern)?;
+        let grep = query.grep.map(Regex::new).transpose()?;
+        let since = query.since.map(parse_when).transpose()?;
+        let until = query.until.map(parse_when).transpose()?;
+        Ok(self.entries
+            .iter()
+            .filter(|entry| since.is_none_or(|since| entry.creation_time >= since))
+            .filter(|entry| until.is_none_or(|until| entry.creation_time < until))
+            .filter(|entry| {
+                regex.is_match(entry.file_path_str())
+                    || (entry.is_obfuscated(&self.config)
+                        && regex.is_match(&entry.file_name(&self.config)))
+            })
+            .filter(|entry| {
+                query.tags.iter().all(|tag| entry.has_tag(&self.aliases.normalize(tag)))
+            })
+            .filter(|entry| grep.as_ref().is_none_or(|grep| match entry.body(&self.config) {
+                Ok(body) => grep.is_match(&body),
+                Err(e) => {
+                    log::warn!("Can not search {}: {}", entry.file_path.display(), e);
+                    false
+                }
+            }))
+            .collect())
+    }
+
+    /// Opens text in the editor from a private scratch file, returning the saved text
+    fn edit_text(&self, text: &str) -> Result<String, JrnError> {
+        let scratch = crypt::Scratch::new(text)?;
+        self.config.launch_editor(Some(scratch.path()))?;
+        scratch.read()
+    }
+
+    /// Writes a new age identity to path, or the IdentityFile setting,
+    /// returning where it was written and its public key
+    ///
+    /// An existing identity is never replaced
+    pub fn keygen(&self, path: Option<&Path>) -> Result<(PathBuf, String), JrnError> {
+        let path = path.map_or_else(|| self.config.get_identity_file(), Path::to_path_buf);
+        let public = crypt::Keys::generate(&path)?;
+        Ok((path, public))
+    }
+
+    /// The tags matching pattern and their counts, most used first
+    pub fn tags_matching(&self, pattern: &str) -> Result<Vec<CountAndTag>, JrnError> {
+        let regex = Regex::new(pattern)?;
+        Ok(self.tags.sorted().into_iter().filter(|tag| regex.is_match(&tag.1)).collect())
+    }
+
+    /// The paths of the entries carrying tag
+    pub fn tag_entries(&self, tag: &str) -> &[PathBuf] {
+        self.tags.entries(tag).unwrap_or_default()
+    }
+
+    /// Writes every entry as a static html site into out_dir, see [export::write_site]
+    pub fn export_html(&self, out_dir: &Path) -> Result<(), JrnError> {
+        export::write_site(out_dir, &self.entries, &self.tags, &self.config)
+    }
+
+    /// Writes the entries matching query as one Markdown document to out,
+    /// see [export::write_markdown]
+    pub fn export_markdown<W: Write>(
+        &self,
+        query: &EntryQuery,
+        out: &mut W,
+    ) -> Result<(), JrnError> {
+        export::write_markdown(out, &self.select(query)?, &self.config)?;
         Ok(())
     }
 
-    pub fn push_tag(&mut self, tag: &str, _descriptor: Option<String>) {
-        //TODO search for descriptor
+    /// Statistics of the entries matching query, keeping the top most used tags, see [Stats]
+    pub fn stats(&self, query: &EntryQuery, top: usize) -> Result<Stats, JrnError> {
+        let today = Local::now().naive_local().date();
+        Ok(Stats::new(self.select(query)?, &self.config, today, top))
+    }
+
+    /// The number of entries matching query on each day of month,
+    /// see [calendar::parse_month] for the months accepted
+    pub fn calendar(&self, month: Option<&str>, query: &EntryQuery) -> Result<Calendar, JrnError> {
+        let today = Local::now().naive_local().date();
+        let (year, month) = calendar::parse_month(month, today)
+            .ok_or_else(|| JrnError::InvalidTime(String::from(month.unwrap_or_default())))?;
 
-        self.tags.insert(tag);
-        //push only to the last entry if not specified
-        let len = self.entries.len();
-        if len > 0 {
-            let entry = self.entries.get_mut(len - 1).unwrap();
-            if let Err(e) = entry.push_tag(tag, &self.config) {
-                self.tags.remove(tag);
-                log::error!("{}", e);
+        let zone = self.config.get_display_zone();
+        let mut counts = BTreeMap::new();
+        for entry in self.select(query)? {
+            *counts.entry(entry.creation_time.in_zone(zone).date()).or_default() += 1;
+        }
+        Ok(Calendar { year, month, counts })
+    }
+
+    /// The entries matching query written on day, oldest first
+    pub fn day(&self, day: &str, query: &EntryQuery) -> Result<Vec<&JrnEntry>, JrnError> {
+        let day = parse_when(day)?.date();
+        let zone = self.config.get_display_zone();
+        Ok(self.select(query)?
+            .into_iter()
+            .filter(|entry| entry.creation_time.in_zone(zone).date() == day)
+            .collect())
+    }
+
+    /// Imports every record found at path as a new entry, see [ImportFormat]
+    ///
+    /// tags are added to every imported entry. Records are skipped if an entry
+    /// already exists at the same time, with dry_run nothing is written
+    ///
+    /// A record that would get the same file name as an earlier one in the file
+    /// is moved on to the next free minute
+    pub fn import(
+        &mut self,
+        format: ImportFormat,
+        path: &Path,
+        tags: &[String],
+        dry_run: bool,
+    ) -> Result<Vec<Imported>, JrnError> {
+        let records = format.read(path)?;
+        let existing: HashSet<_> = self.entries.iter().map(|e| e.creation_time.to_utc()).collect();
+        let mut names = HashSet::new();
+        let mut imported = Vec::new();
+        let mut operations = Vec::new();
+
+        for record in records {
+            let mut entry_tags = record.tags;
+            entry_tags.extend(tags.iter().cloned());
+            let location = record.location.clone();
+            let mut entry = JrnEntry::new(self, Some(record.creation_time), entry_tags, location);
+            // records without a location keep none, rather than that of the last entry
+            if record.location.is_none() {
+                entry.location = Location::default();
+            }
+            if existing.contains(&entry.creation_time.to_utc()) {
+                let name = self.display_path(&entry.file_path);
+                imported.push(Imported { name, skipped: true });
+                continue;
             }
+
+            let mut time = record.creation_time;
+            while names.contains(&entry.file_path)
+                || entry.file_path.exists()
+                || existing.contains(&entry.creation_time.to_utc())
+            {
+                time = time.plus_minutes(1);
+                entry.set_time(time, &self.config);
+            }
+            names.insert(entry.file_path.clone());
+            let name = self.display_path(&entry.file_path);
+
+            if !dry_run {
+                entry.write_body(&self.config, &record.body)?;
+                let path = relative(&self.root_path, &entry.file_path);
+                operations.push(Operation::Create { path });
+                self.entries.push(entry);
+                self.track(self.entries.len() - 1);
+            }
+            imported.push(Imported { name, skipped: false });
         }
+        self.entries.sort();
+        self.record("import", operations);
+        Ok(imported)
+    }
+
+    /// Overrides the Color setting for this run
+    pub fn set_color(&mut self, choice: ColorChoice) {
+        self.config.set(JrnSetting::Color, choice.as_str());
+    }
+
+    /// All entries in this repo, oldest first
+    pub fn entries(&self) -> &[JrnEntry] {
+        &self.entries
     }
 
-    /// Removes the newest entry
-    pub fn remove_latest(&mut self) -> io::Result<()> {
-        if let Some(removed) = self.entries.pop() {
-            for tag in &removed.tags {
-                self.remove_tag(tag);
+    /// The tags of the entries below root, most used first, without opening a repo
+    ///
+    /// Only file names are read and nothing is written, so this is cheap enough to run
+    /// on every completion. Obfuscated entries are left out, see [JrnEntry::read_name]
+    pub fn tags_below(
+        root: &Path,
+        config: &Settings,
+        ignore: &IgnorePatterns,
+        aliases: &TagAliases,
+    ) -> Vec<CountAndTag> {
+        let mut tags = TagContainer::new();
+        for entry in walk::collect_names(root, config, ignore, aliases) {
+            for tag in &entry.tags {
+                tags.insert(tag, &entry.file_path);
             }
-            removed.delete()?;
         }
+        tags.sorted()
+    }
+
+    /// The ids of the n most recent entries, newest first
+    pub fn recent_entry_ids(&self, n: usize) -> Vec<String> {
+        self.entries.iter().rev().take(n).map(JrnEntry::id).collect()
+    }
+
+    /// The tags matching pattern as a tree of their hierarchy,
+    /// counting each tag together with its descendants, see [TagContainer::tree]
+    pub fn tag_tree(&self, pattern: &str) -> Result<Vec<TagNode>, JrnError> {
+        let regex = Regex::new(pattern)?;
+        Ok(self.tags.tree(|t| regex.is_match(t)))
+    }
+
+    /// Pushes a tag to the entry found by descriptor, see [JrnRepo::find_entry]
+    /// pushes to the most recent entry if no descriptor is given
+    ///
+    /// The rename goes ahead without asking, see [Plan::asks]
+    pub fn push_tag<F>(
+        &mut self,
+        tag: &str,
+        descriptor: Option<&str>,
+        confirm: F,
+    ) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let index = self.find_entry(descriptor.unwrap_or("HEAD"))?;
+        let entry = &self.entries[index];
+        let tag = self.aliases.normalize(tag);
+        let mut plan = Plan::new("push-tag");
+        if !tag.is_empty() && !entry.tags.contains(&tag) {
+            let mut tags = entry.tags.clone();
+            tags.push(tag.clone());
+            let new_name = entry.file_name_with_tags(&tags, &self.config);
+            plan.push(format!("{} -> {}", entry.file_name(&self.config), new_name));
+        }
+        if !plan.asking_if(false).confirmed_by(confirm)? {
+            return Ok(());
+        }
+
+        let renamed = self.change_entry(index, |entry, config, aliases| {
+            entry.push_tag(&tag, config, aliases)
+        })?;
+        self.finish("push-tag", renamed.into_iter().collect())
+    }
+
+    /// Removes a tag from the entry found by descriptor, see [JrnRepo::find_entry]
+    /// removes from the most recent entry if no descriptor is given
+    pub fn remove_tag(&mut self, tag: &str, descriptor: Option<&str>) -> Result<(), JrnError> {
+        let index = self.find_entry(descriptor.unwrap_or("HEAD"))?;
+        let renamed = self.change_entry(index, |entry, config, aliases| {
+            entry.remove_tag(tag, config, aliases)
+        })?;
+        self.finish("remove-tag", renamed.into_iter().collect())
+    }
+
+    /// Renames tag to new_name in every entry carrying it, see [tag::rename]
+    ///
+    /// Descendants such as tag/child are renamed along.
+    /// Changing more than one entry asks for confirmation, see [Plan::asks]
+    pub fn rename_tag<F>(&mut self, tag: &str, new_name: &str, confirm: F) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let tag = self.aliases.normalize(tag);
+        let new_name = self.aliases.normalize(new_name);
+        self.retag("tags --new-name", confirm, |t| {
+            Some(tag::rename(t, &tag, &new_name).unwrap_or_else(|| String::from(t)))
+        })
+    }
+
+    /// Deletes every tag matching pattern from the entries carrying it
+    ///
+    /// Changing more than one entry asks for confirmation, see [Plan::asks]
+    pub fn delete_tags<F>(&mut self, pattern: &str, confirm: F) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let regex = Regex::new(pattern)?;
+        self.retag("tags --delete", confirm, |t| match regex.is_match(t) {
+            true => None,
+            false => Some(String::from(t)),
+        })
+    }
+
+    /// Maps every tag of every entry through retag, dropping the tags it maps to None
+    fn retag<F, R>(&mut self, command: &str, confirm: F, retag: R) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+        R: Fn(&str) -> Option<String>,
+    {
+        let mut plan = Plan::new(command);
+        let mut changes = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let tags = entry.tags.iter().filter_map(|t| retag(t)).collect::<Vec<_>>();
+            let tags = self.aliases.normalize_all(&tags);
+            if tags != entry.tags {
+                let new_name = entry.file_name_with_tags(&tags, &self.config);
+                plan.push(format!("{} -> {}", entry.file_name(&self.config), new_name));
+                changes.push((index, tags));
+            }
+        }
+        let ask = plan.len() > 1;
+        if !plan.asking_if(ask).confirmed_by(confirm)? {
+            return Ok(());
+        }
+        self.set_tags(command, changes)
+    }
+
+    /// Gives each entry at index its tags, recording the renames under command
+    fn set_tags(
+        &mut self,
+        command: &str,
+        changes: Vec<(usize, Vec<String>)>,
+    ) -> Result<(), JrnError> {
+        let mut renamed = Vec::new();
+        let mut result = Ok(());
+        for (index, tags) in changes {
+            let change = |entry: &mut JrnEntry, config: &Settings, aliases: &TagAliases| {
+                entry.set_tags(&tags, config, aliases)
+            };
+            match self.change_entry(index, change) {
+                Ok(r) => renamed.extend(r),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.entries.sort();
+        // entries renamed before a failure can still be undone and linked to
+        self.finish(command, renamed)?;
+        result
+    }
+
+    /// Removes the entry found by descriptor to the trash, see [JrnRepo::find_entry] and [trash]
+    ///
+    /// Confirmation is always asked for
+    pub fn remove_entry<F>(&mut self, descriptor: &str, confirm: F) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let index = self.find_entry(descriptor)?;
+        let mut plan = Plan::new("rm");
+        plan.push(format!("remove   {}", self.display_path(&self.entries[index].file_path)));
+        if !plan.confirmed_by(confirm)? {
+            return Ok(());
+        }
+
+        let trashed = self.trash_entry(index)?;
+        let path = relative(&self.root_path, &trashed.file_path);
+        let trashed = relative(&self.root_path, &trashed.item);
+        self.record("rm", vec![Operation::Remove { path, trashed }]);
         Ok(())
     }
 
-    fn remove_tag(&mut self, tag: &str) {
-        //TODO implement
-        println!("removing tag: {}", tag);
+    /// Copies file into the attachments of the entry found by descriptor and links it,
+    /// returning the path of the copy, see [JrnEntry::attach]
+    ///
+    /// Goes ahead without asking, returns None if confirm declines, see [Plan::asks]
+    pub fn attach<F>(
+        &self,
+        descriptor: &str,
+        file: &Path,
+        confirm: F,
+    ) -> Result<Option<PathBuf>, JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let entry = self.entry(descriptor)?;
+        let mut plan = Plan::new("attach");
+        let path = self.display_path(&entry.file_path);
+        plan.push(format!("attach   {} to {}", file.display(), path));
+        if !plan.asking_if(false).confirmed_by(confirm)? {
+            return Ok(None);
+        }
+        entry.attach(file, &self.config).map(Some)
     }
 
-    /// Helper method to walk the filesystem and add entries
-    fn collect_entries(&mut self) {
-        let path = self.root_path.clone();
-        fn collect(repo: &mut JrnRepo, path: &Path) {
-            if repo.ignore.matches(path) {
-                return;
-            }
-
-            if path.is_dir() {
-                if let Ok(dir) = fs::read_dir(path) {
-                    for f in dir {
-                        if let Ok(file) = f {
-                            collect(repo, &file.path());
-                        }
+    /// Reverses the last n commands in the undo log, most recent first, see [undo]
+    ///
+    /// Created entries are moved to the trash, renamed entries get their old name back
+    /// and removed entries are restored from the trash.
+    /// An operation that can not be reversed is kept in the log,
+    /// together with the operations of its command not reversed yet.
+    /// Confirmation is always asked for, there is nothing to confirm if the log is empty
+    pub fn undo<F>(&mut self, n: usize, confirm: F) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let log = UndoLog::at(&self.root_path);
+        let mut records = log.read()?;
+        let mut plan = Plan::new("undo");
+        for record in records.iter().rev().take(n) {
+            plan.push(format!("undo {} from {}", record.command, record.time));
+            for operation in record.operations.iter().rev() {
+                plan.push(match operation {
+                    Operation::Create { path } => format!("    trash    {}", path.display()),
+                    Operation::Rename { path, from } => {
+                        format!("    rename   {} -> {}", path.display(), from)
                     }
+                    Operation::Remove { path, .. } => format!("    restore  {}", path.display()),
+                });
+            }
+        }
+        if !plan.confirmed_by(confirm)? {
+            return Ok(());
+        }
+
+        for _ in 0..n {
+            let record = match records.last_mut() {
+                Some(record) => record,
+                None => break,
+            };
+            while let Some(operation) = record.operations.last() {
+                if let Err(e) = self.reverse(operation) {
+                    log.write(&records)?;
+                    return Err(e);
                 }
-            } else if let Some(entry) = JrnEntry::read_entry(path, &repo.config) {
-                for tag in &entry.tags {
-                    repo.tags.insert(tag);
+                record.operations.pop();
+            }
+            records.pop();
+            log.write(&records)?;
+        }
+        Ok(())
+    }
+
+    /// Reverses a single operation of the undo log
+    fn reverse(&mut self, operation: &Operation) -> Result<(), JrnError> {
+        match operation {
+            Operation::Create { path } => {
+                let path = self.root_path.join(path);
+                match self.entries.iter().position(|e| e.file_path == path) {
+                    Some(index) => {
+                        self.trash_entry(index)?;
+                    }
+                    None => log::warn!("{} is already gone", path.display()),
+                }
+            }
+            Operation::Rename { path, from } => {
+                let path = self.root_path.join(path);
+                let index = self.entries
+                    .iter()
+                    .position(|e| e.file_path == path)
+                    .ok_or_else(|| JrnError::EntryNotFound(path.display().to_string()))?;
+                let renamed = self.change_entry(index, |entry, config, aliases| {
+                    entry.rename(from, config, aliases)
+                })?;
+                self.entries.sort();
+                self.relink(&renamed.into_iter().map(|r| r.names).collect::<Vec<_>>())?;
+            }
+            Operation::Remove { path, trashed } => {
+                let path = self.root_path.join(path);
+                let item = self.root_path.join(trashed);
+                if !item.exists() {
+                    log::warn!("{} is no longer in the trash", path.display());
+                    return Ok(());
                 }
-                repo.entries.push(entry);
+                trash::restore(&item, &path)?;
+                self.add_entry(&path);
+            }
+        }
+        Ok(())
+    }
+
+    /// The entries in the trash, oldest first, see [trash]
+    pub fn trash(&self) -> Result<Vec<TrashItem>, JrnError> {
+        trash::list(&self.root_path)
+    }
+
+    /// Puts the entry in the trash with id, or a unique prefix of it,
+    /// back where it was removed from
+    ///
+    /// Goes ahead without asking, see [Plan::asks]
+    pub fn restore_trash<F>(&mut self, id: &str, confirm: F) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let item = trash::find(&self.root_path, id)?;
+        let path = self.root_path.join(&item.path);
+        let mut plan = Plan::new("trash restore");
+        plan.push(format!("restore  {}", self.display_path(&path)));
+        if !plan.asking_if(false).confirmed_by(confirm)? {
+            return Ok(());
+        }
+        trash::restore(&item.dir, &path)?;
+        self.add_entry(&path);
+        self.record("trash-restore", vec![Operation::Create { path: item.path }]);
+        Ok(())
+    }
+
+    /// Permanently deletes the entries in the trash,
+    /// or only those removed longer ago than older_than
+    ///
+    /// older_than is an age such as 30d, 2w or 12h, see [trash::parse_age].
+    /// Confirmation is always asked for
+    pub fn empty_trash<F>(&self, older_than: Option<&str>, confirm: F) -> Result<(), JrnError>
+    where
+        F: FnOnce(&Plan) -> Result<bool, JrnError>,
+    {
+        let age = older_than
+            .map(|age| {
+                trash::parse_age(age).ok_or_else(|| JrnError::InvalidTime(String::from(age)))
+            })
+            .transpose()?;
+        let now = Local::now();
+        let items: Vec<_> = trash::list(&self.root_path)?
+            .into_iter()
+            .filter(|item| age.is_none_or(|age| now.signed_duration_since(item.time) >= age))
+            .collect();
+
+        let mut plan = Plan::new("trash empty");
+        for item in &items {
+            plan.push(format!("delete   {}  {}", item.id, item.path.display()));
+        }
+        if !plan.confirmed_by(confirm)? {
+            return Ok(());
+        }
+        for item in items {
+            std::fs::remove_dir_all(&item.dir)?;
+        }
+        Ok(())
+    }
+
+    /// Applies change to the entry at index, keeping the tag index up to date
+    ///
+    /// Returns how the entry was renamed, if it was. Links to it are left
+    /// for [JrnRepo::finish] to rewrite, once for every entry a command renames
+    fn change_entry<F>(&mut self, index: usize, change: F) -> Result<Option<Renamed>, JrnError>
+    where
+        F: FnOnce(&mut JrnEntry, &Settings, &TagAliases) -> Result<(), JrnError>,
+    {
+        let old = Names::of(&self.entries[index], &self.config);
+        let from = self.entries[index].stored_name(&self.config);
+        self.untrack(index);
+        let result = change(&mut self.entries[index], &self.config, &self.aliases);
+        self.track(index);
+        result?;
+
+        let entry = &self.entries[index];
+        Ok(match entry.stored_name(&self.config) != from {
+            true => Some(Renamed {
+                names: (old, Names::of(entry, &self.config)),
+                operation: Operation::Rename {
+                    path: relative(&self.root_path, &entry.file_path),
+                    from,
+                },
+            }),
+            false => None,
+        })
+    }
+
+    /// Records the renames of command in the undo log and rewrites the links to the renamed entries
+    fn finish(&self, command: &str, renamed: Vec<Renamed>) -> Result<(), JrnError> {
+        let (names, operations): (Vec<_>, Vec<_>) =
+            renamed.into_iter().map(|r| (r.names, r.operation)).unzip();
+        self.record(command, operations);
+        self.relink(&names)
+    }
+
+    /// Moves the entry at index to the trash and forgets it, see [trash::put]
+    fn trash_entry(&mut self, index: usize) -> Result<Trashed, JrnError> {
+        let item = trash::put(&self.root_path, &self.entries[index], &self.config)?;
+        self.untrack(index);
+        let entry = self.entries.remove(index);
+        Ok(Trashed { file_path: entry.file_path, item })
+    }
+
+    /// Reads the entry put back at path and adds it to this repo
+    fn add_entry(&mut self, path: &Path) {
+        match JrnEntry::read_entry(path, &self.config, &self.aliases) {
+            Some(entry) => {
+                self.entries.push(entry);
+                self.track(self.entries.len() - 1);
+                self.entries.sort();
+            }
+            None => log::warn!("{} is no longer an entry", path.display()),
+        }
+    }
+
+    /// path relative to the root of this repo, for display
+    fn display_path(&self, path: &Path) -> String {
+        relative(&self.root_path, path).display().to_string()
+    }
+
+    /// Records the operations of command in the undo log
+    ///
+    /// The operations are already done, so failing to record them is only warned about
+    fn record(&self, command: &str, operations: Vec<Operation>) {
+        if let Err(e) = UndoLog::at(&self.root_path).append(&Record::now(command, operations)) {
+            log::warn!("Can not record {} in the undo log: {}", command, e);
+        }
+    }
+
+    /// Reads the entry at index again from its file
+    ///
+    /// The file is renamed if its tags are no longer in canonical form, returning how,
+    /// an entry whose file is gone is dropped
+    fn reload_entry(&mut self, index: usize) -> Result<Option<Renamed>, JrnError> {
+        self.untrack(index);
+        let old = &self.entries[index];
+        let read = match old.file_path.exists() {
+            true => JrnEntry::read_entry(&old.file_path, &self.config, &self.aliases),
+            false => None,
+        };
+
+        match read {
+            Some(entry) => {
+                self.entries[index] = entry;
+                let renamed = self.rewrite_entry(index);
+                self.track(index);
+                self.entries.sort();
+                renamed
+            }
+            None => {
+                log::warn!("{} is no longer an entry", old.file_path.display());
+                self.entries.remove(index);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Renames the file of the entry at index if its tags are not in canonical form,
+    /// returning how, see [JrnEntry::rewrite_file_path]
+    ///
+    /// Entries are normalized when read, so the old name is taken from the file
+    fn rewrite_entry(&mut self, index: usize) -> Result<Option<Renamed>, JrnError> {
+        let (config, root) = (&self.config, &self.root_path);
+        let entry = &mut self.entries[index];
+        let old = Names::of(entry, config);
+        let from = entry.stored_name(config);
+        if !entry.rewrite_file_path(config)? {
+            return Ok(None);
+        }
+        Ok(Some(Renamed {
+            names: (old, Names::of(entry, config)),
+            operation: Operation::Rename { path: relative(root, &entry.file_path), from },
+        }))
+    }
+
+    /// Records the tags of the entry at index
+    fn track(&mut self, index: usize) {
+        let entry = &self.entries[index];
+        for tag in &entry.tags {
+            self.tags.insert(tag, &entry.file_path);
+        }
+    }
+
+    /// Forgets every tag reference held by the entry at index
+    fn untrack(&mut self, index: usize) {
+        let entry = &self.entries[index];
+        for tag in &entry.tags {
+            self.tags.remove(tag, &entry.file_path);
+        }
+    }
+
+    /// Helper method to walk the filesystem and add entries
+    fn collect_entries(&mut self) {
+        self.entries =
+            walk::collect_entries(&self.root_path, &self.config, &self.ignore, &self.aliases);
+        self.index_tags();
+    }
+
+    /// Rebuilds the tag container from the entries in this repo
+    fn index_tags(&mut self) {
+        self.tags = TagContainer::new();
+        for entry in &self.entries {
+            for tag in &entry.tags {
+                self.tags.insert(tag, &entry.file_path);
             }
         }
-        collect(self, &path);
-        self.entries.sort();
     }
 }
 
+/// An entry moved to the trash
+struct Trashed {
+    /// where the entry was
+    file_path: PathBuf,
+    /// the directory in the trash now holding it
+    item: PathBuf,
+}
+
+/// path relative to root, as recorded in the undo log and shown in plans
+fn relative(root: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
+}
+
+/// Parses a time given on the command line, see [TimeStamp::parse_when]
+fn parse_when(when: &str) -> Result<TimeStamp, JrnError> {
+    TimeStamp::parse_when(when, Local::now())
+        .ok_or_else(|| JrnError::InvalidTime(String::from(when)))
+}
+
 impl Deref for JrnRepo {
     type Target = Settings;
 
@@ -182,3 +1140,446 @@ impl Deref for JrnRepo {
         &self.config
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn repo_with(names: &[&str]) -> (tempfile::TempDir, JrnRepo) {
+        let dir = tempfile::tempdir().unwrap();
+        for name in names {
+            std::fs::File::create(dir.path().join(name)).unwrap();
+        }
+        let repo = JrnRepo::init_at(
+            dir.path().to_path_buf(),
+            Settings::default(),
+            IgnorePatterns::find_or_default(),
+            TagAliases::default(),
+        ).unwrap();
+        (dir, repo)
+    }
+
+    #[test]
+    fn opens_repos_with_their_configuration() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".jrnaliases"), "mtg = meeting\n").unwrap();
+        std::fs::write(dir.path().join(".jrnignore"), "^old$\n").unwrap();
+        std::fs::create_dir(dir.path().join("old")).unwrap();
+        for name in &["2019-10-01_0930-mtg", "2019-10-03_0930-meeting", "old/2019-09-01_0930"] {
+            std::fs::File::create(dir.path().join(name)).unwrap();
+        }
+
+        let repo = JrnRepo::open(dir.path()).unwrap();
+        assert_eq!(repo.entries().len(), 2);
+        let tags = repo.tags_matching("^meet").unwrap();
+        assert_eq!(tags, vec![CountAndTag(2, String::from("meeting"))]);
+        let calendar = repo.calendar(Some("2019-10"), &EntryQuery::default()).unwrap();
+        let days: Vec<_> = calendar.counts.keys().map(|day| day.to_string()).collect();
+        assert_eq!(days, ["2019-10-01", "2019-10-03"]);
+    }
+
+    #[test]
+    fn migrates_legacy_tag_names_only() {
+        let names =
+            ["2019-10-01_0930-c++_work+projectx", "2019-10-02_0930-a:b", "2019-10-03_0930-50%25"];
+        let (dir, mut repo) = repo_with(&names);
+        assert!(names.iter().all(|name| dir.path().join(name).exists()));
+
+        repo.migrate_tags(|plan| {
+            let change =
+                "2019-10-01_0930-c++_work+projectx -> 2019-10-01_0930-c%2B%2B_work+projectx";
+            assert_eq!(plan.changes(), [change]);
+            Ok(true)
+        }).unwrap();
+        assert_eq!(repo.entries[0].tags, vec!["c++", "work/projectx"]);
+        assert_eq!(repo.entries[1].tags, vec!["a:b"]);
+        assert_eq!(repo.entries[2].tags, vec!["50%"]);
+        assert!(names[1..].iter().all(|name| dir.path().join(name).exists()));
+
+        repo.undo(1, |_| Ok(true)).unwrap();
+        assert!(names.iter().all(|name| dir.path().join(name).exists()));
+    }
+
+    #[test]
+    fn finds_entries_by_descriptor() {
+        let (_dir, repo) =
+            repo_with(&["2019-10-01_0930-a", "2019-10-02_0930-b", "2019-10-03_0930-c"]);
+        assert_eq!(repo.find_entry("HEAD").unwrap(), 2);
+        assert_eq!(repo.find_entry("HEAD~2").unwrap(), 0);
+        assert!(repo.find_entry("HEAD~3").is_err());
+        assert_eq!(repo.find_entry("2019-10-02_0930-b").unwrap(), 1);
+
+        let id = repo.entries[1].id();
+        assert_eq!(repo.find_entry(&id).unwrap(), 1);
+        match repo.find_entry("") {
+            Err(JrnError::AmbiguousEntry(_)) => (),
+            other => panic!("expected an ambiguous entry, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn retime_renames_and_sorts() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-a", "2019-10-02_0930-b"]);
+        repo.retime("HEAD~1", "2019-10-03 21:30", |_| Ok(true)).unwrap();
+
+        let moved = dir.path().join("2019-10-03_2130-a");
+        assert!(moved.exists());
+        assert!(!dir.path().join("2019-10-01_0930-a").exists());
+        assert_eq!(repo.entries[1].file_path, moved);
+        assert_eq!(repo.tags.entries("a"), Some(&[moved][..]));
+    }
+
+    #[test]
+    fn dry_runs_of_single_renames_change_nothing() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-a"]);
+        let mut plans = Vec::new();
+        let mut dry_run = |plan: &Plan| {
+            assert!(!plan.asks());
+            plans.extend(plan.changes().to_vec());
+            Ok(false)
+        };
+        repo.retime("HEAD", "2019-10-03 21:30", &mut dry_run).unwrap();
+        repo.push_tag("b", None, &mut dry_run).unwrap();
+        repo.push_tag("a", None, &mut dry_run).unwrap();
+        assert_eq!(plans, vec![
+            "2019-10-01_0930-a -> 2019-10-03_2130-a",
+            "2019-10-01_0930-a -> 2019-10-01_0930-a_b",
+        ]);
+        assert!(dir.path().join("2019-10-01_0930-a").exists());
+        assert_eq!(repo.entries[0].tags, vec!["a"]);
+    }
+
+    #[test]
+    fn removes_tags() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-a_b"]);
+        repo.remove_tag("a", None).unwrap();
+        repo.remove_tag("c", None).unwrap();
+
+        let renamed = dir.path().join("2019-10-01_0930-b");
+        assert!(renamed.exists());
+        assert_eq!(repo.entries[0].tags, vec![String::from("b")]);
+        assert_eq!(repo.tags.entries("a"), None);
+    }
+
+    #[test]
+    fn undoes_commands_in_reverse() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-a"]);
+        let original = dir.path().join("2019-10-01_0930-a");
+        std::fs::write(&original, "text").unwrap();
+        repo.push_tag("b", None, |_| Ok(true)).unwrap();
+        repo.retime("HEAD", "2019-10-02 09:30", |_| Ok(true)).unwrap();
+        repo.rename_tag("b", "c", |_| Ok(true)).unwrap();
+        repo.remove_entry("HEAD", |_| Ok(true)).unwrap();
+        repo.create_entry(vec![], None, Some("2019-10-03 09:30"), true).unwrap();
+        assert_eq!(repo.entries.len(), 1);
+
+        repo.undo(2, |_| Ok(true)).unwrap();
+        assert_eq!(repo.entries.len(), 1);
+        assert_eq!(repo.entries[0].file_path, dir.path().join("2019-10-02_0930-a_c"));
+
+        repo.undo(5, |_| Ok(true)).unwrap();
+        assert_eq!(repo.entries[0].file_path, original);
+        assert_eq!(std::fs::read_to_string(&original).unwrap(), "text");
+        assert_eq!(repo.tags.entries("b"), None);
+        assert!(UndoLog::at(dir.path()).read().unwrap().is_empty());
+    }
+
+    #[test]
+    fn undoes_normalized_file_names() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-a_a"]);
+        repo.normalize_tags(|_| Ok(true)).unwrap();
+        assert_eq!(repo.entries[0].file_path, dir.path().join("2019-10-01_0930-a"));
+
+        repo.undo(1, |_| Ok(true)).unwrap();
+        assert_eq!(repo.entries[0].file_path, dir.path().join("2019-10-01_0930-a_a"));
+        assert!(dir.path().join("2019-10-01_0930-a_a").exists());
+    }
+
+    #[test]
+    fn keeps_operations_not_undone_in_the_log() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-a", "2019-10-02_0930-a"]);
+        repo.rename_tag("a", "b", |_| Ok(true)).unwrap();
+        std::fs::remove_file(dir.path().join("2019-10-01_0930-b")).unwrap();
+
+        assert!(repo.undo(1, |_| Ok(true)).is_err());
+        assert!(dir.path().join("2019-10-02_0930-a").exists());
+        let records = UndoLog::at(dir.path()).read().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].operations, vec![Operation::Rename {
+            path: PathBuf::from("2019-10-01_0930-b"),
+            from: String::from("2019-10-01_0930-a"),
+        }]);
+    }
+
+    #[test]
+    fn trashes_and_restores_entries() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-a", "2019-10-02_0930-b"]);
+        repo.remove_entry("HEAD~1", |_| Ok(true)).unwrap();
+        repo.remove_entry("HEAD", |_| Ok(true)).unwrap();
+        let reopened = JrnRepo::init_at(
+            dir.path().to_path_buf(),
+            Settings::default(),
+            IgnorePatterns::find_or_default(),
+            TagAliases::default(),
+        ).unwrap();
+        assert!(reopened.entries.is_empty());
+
+        let items = trash::list(dir.path()).unwrap();
+        assert_eq!(items.len(), 2);
+        repo.restore_trash(&items[0].id, |_| Ok(true)).unwrap();
+        assert!(dir.path().join("2019-10-01_0930-a").exists());
+        assert_eq!(repo.entries.len(), 1);
+
+        repo.empty_trash(Some("30d"), |_| Ok(true)).unwrap();
+        assert_eq!(trash::list(dir.path()).unwrap().len(), 1);
+        repo.empty_trash(None, |_| Ok(true)).unwrap();
+        assert!(trash::list(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn deletes_tags_once_confirmed() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-a_b", "2019-10-02_0930-b"]);
+        repo.delete_tags("^b$", |plan| {
+            assert!(plan.asks());
+            assert_eq!(plan.changes(), [
+                "2019-10-01_0930-a_b -> 2019-10-01_0930-a",
+                "2019-10-02_0930-b -> 2019-10-02_0930",
+            ]);
+            Ok(false)
+        }).unwrap();
+        assert!(dir.path().join("2019-10-01_0930-a_b").exists());
+
+        repo.delete_tags("^b$", |_| Ok(true)).unwrap();
+        assert!(dir.path().join("2019-10-01_0930-a").exists());
+        assert!(dir.path().join("2019-10-02_0930").exists());
+        assert_eq!(repo.tags.entries("b"), None);
+    }
+
+    #[test]
+    fn creates_backdated_entries() {
+        let (dir, mut repo) = repo_with(&["2019-10-02_0930-b"]);
+        repo.create_entry(vec![String::from("a")], None, Some("2019-10-01 08:00"), true).unwrap();
+        assert!(dir.path().join("2019-10-01_0800-a").exists());
+        assert_eq!(repo.find_entry("HEAD~1").unwrap(), 0);
+        assert!(repo.create_entry(vec![], None, Some("not a time"), true).is_err());
+    }
+
+    #[test]
+    fn reload_normalizes_and_drops_missing() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-mtg"]);
+        repo.aliases.insert("mtg", "meeting");
+        repo.reload_entry(0).unwrap();
+        assert!(dir.path().join("2019-10-01_0930-meeting").exists());
+        assert_eq!(repo.tags.count("mtg"), None);
+        assert_eq!(repo.tags.count("meeting"), Some(1));
+
+        std::fs::remove_file(dir.path().join("2019-10-01_0930-meeting")).unwrap();
+        repo.reload_entry(0).unwrap();
+        assert!(repo.entries.is_empty());
+        assert_eq!(repo.tags.count("meeting"), None);
+    }
+
+    #[test]
+    fn import_skips_existing_times() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-a"]);
+        let journal = dir.path().join(".journal.txt");
+        std::fs::write(&journal, "[2019-10-01 09:30] taken @b\n[2019-10-02 10:00] new @b\n")
+            .unwrap();
+
+        repo.import(ImportFormat::Jrnl, &journal, &[], true).unwrap();
+        assert_eq!(repo.entries.len(), 1);
+        assert!(!dir.path().join("2019-10-02_1000-b").exists());
+
+        repo.import(ImportFormat::Jrnl, &journal, &[String::from("imported")], false).unwrap();
+        assert_eq!(repo.entries.len(), 2);
+        let imported = dir.path().join("2019-10-02_1000-b_imported");
+        assert_eq!(std::fs::read_to_string(&imported).unwrap(), "new @b\n");
+        assert_eq!(repo.tags.count("b"), Some(1));
+    }
+
+    #[test]
+    fn import_moves_records_of_the_same_minute() {
+        let (dir, mut repo) = repo_with(&[]);
+        let journal = dir.path().join(".journal.txt");
+        std::fs::write(&journal, "[2019-10-01 09:30] first\n[2019-10-01 09:30] second\n").unwrap();
+
+        let imported = repo.import(ImportFormat::Jrnl, &journal, &[], true).unwrap();
+        assert!(imported.iter().all(|record| !record.skipped));
+        assert_eq!(imported[1].name, "2019-10-01_0931");
+
+        repo.import(ImportFormat::Jrnl, &journal, &[], false).unwrap();
+        let read = |name| std::fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read("2019-10-01_0930"), "first\n");
+        assert_eq!(read("2019-10-01_0931"), "second\n");
+
+        let imported = repo.import(ImportFormat::Jrnl, &journal, &[], false).unwrap();
+        assert!(imported.iter().all(|record| record.skipped));
+        assert_eq!(repo.entries.len(), 2);
+    }
+
+    #[test]
+    fn imported_locations_survive_reopening() {
+        let (dir, mut repo) = repo_with(&["2019-09-01_0930"]);
+        repo.create_entry(vec![], Some(String::from("Home")), Some("2019-09-02 09:30"), true)
+            .unwrap();
+        let export = dir.path().join(".dayone.json");
+        std::fs::write(&export, r#"{ "entries": [
+            { "creationDate": "2019-10-01T07:30:00Z", "text": "Standup",
+              "location": { "placeName": "Office" } },
+            { "creationDate": "2019-10-02T07:30:00Z", "text": "Nowhere" }
+        ] }"#).unwrap();
+        repo.import(ImportFormat::DayOne, &export, &[], false).unwrap();
+
+        let repo = JrnRepo::open(dir.path()).unwrap();
+        let locations: Vec<_> = repo.entries.iter().map(|e| e.location.as_option()).collect();
+        assert_eq!(locations, [None, Some("Home"), Some("Office"), None]);
+        assert_eq!(repo.entries[2].body(&repo.config).unwrap(), "Standup\n");
+    }
+
+    #[test]
+    fn stats_count_locations_read_from_disk() {
+        let (dir, mut repo) = repo_with(&[]);
+        for (at, location) in [("2019-10-01 09:30", "Berlin"), ("2019-10-02 09:30", "Paris")] {
+            repo.create_entry(vec![], Some(String::from(location)), Some(at), true).unwrap();
+        }
+        repo.create_entry(vec![], Some(String::from("Berlin")), Some("2019-10-03 09:30"), true)
+            .unwrap();
+
+        let repo = JrnRepo::open(dir.path()).unwrap();
+        let stats = repo.stats(&EntryQuery::default(), 10).unwrap();
+        assert_eq!(stats.words, 0);
+        assert_eq!(stats.locations, [
+            LocationCount { location: String::from("Berlin"), count: 2 },
+            LocationCount { location: String::from("Paris"), count: 1 },
+        ]);
+    }
+
+    fn encrypted_repo(obfuscate: bool) -> (tempfile::TempDir, tempfile::TempDir, JrnRepo) {
+        let keys = tempfile::tempdir().unwrap();
+        let identity = keys.path().join("identity.txt");
+        crypt::Keys::generate(&identity).unwrap();
+        let mut config = Settings::default();
+        config.set(JrnSetting::Encrypt, "true");
+        config.set(JrnSetting::ObfuscateFileNames, &obfuscate.to_string());
+        config.set(JrnSetting::IdentityFile, identity.to_str().unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let repo = JrnRepo::init_at(
+            dir.path().to_path_buf(),
+            config,
+            IgnorePatterns::find_or_default(),
+            TagAliases::default(),
+        ).unwrap();
+        (keys, dir, repo)
+    }
+
+    #[test]
+    fn encrypts_entries() {
+        let (_keys, dir, mut repo) = encrypted_repo(false);
+        let work = vec![String::from("work")];
+        repo.create_entry(work, None, Some("2019-10-01 09:30"), true).unwrap();
+        let entry = &repo.entries[0];
+        assert_eq!(entry.file_path, dir.path().join("2019-10-01_0930-work.age"));
+        entry.write_body(&repo.config, "secret plans\n").unwrap();
+        assert!(!std::fs::read_to_string(&entry.file_path).unwrap_or_default().contains("secret"));
+
+        repo.push_tag("home", None, |_| Ok(true)).unwrap();
+        let entry = &repo.entries[0];
+        assert_eq!(entry.file_path, dir.path().join("2019-10-01_0930-work_home.age"));
+        assert_eq!(entry.body(&repo.config).unwrap(), "secret plans\n");
+        assert!(entry.display(&repo.config).to_string().contains("secret plans"));
+    }
+
+    #[test]
+    fn obfuscates_file_names() {
+        let (keys, dir, mut repo) = encrypted_repo(true);
+        let work = vec![String::from("work")];
+        repo.create_entry(work, None, Some("2019-10-01 09:30"), true).unwrap();
+        let path = repo.entries[0].file_path.clone();
+        repo.entries[0].write_body(&repo.config, "secret plans\n").unwrap();
+        repo.retime("HEAD", "2019-10-02 09:30", |_| Ok(true)).unwrap();
+        repo.push_tag("home", None, |_| Ok(true)).unwrap();
+
+        let mut config = Settings::default();
+        config.set(JrnSetting::IdentityFile, keys.path().join("identity.txt").to_str().unwrap());
+        let reopened = JrnRepo::init_at(
+            dir.path().to_path_buf(),
+            config,
+            IgnorePatterns::find_or_default(),
+            TagAliases::default(),
+        ).unwrap();
+        let entry = &reopened.entries[0];
+        assert_eq!(entry.file_path, path);
+        assert_eq!(entry.creation_time, TimeStamp::from_ymdhm(2019, 10, 2, 9, 30).unwrap());
+        assert_eq!(entry.tags, vec!["work", "home"]);
+        assert_eq!(entry.body(&reopened.config).unwrap(), "secret plans\n");
+
+        // without the identity the entry is not found, rather than shown under its random name
+        let (_dir, without_keys) = repo_with(&[]);
+        assert!(JrnEntry::read_entry(&path, &without_keys.config, &without_keys.aliases).is_none());
+    }
+
+    #[test]
+    fn attachments_follow_their_entry() {
+        let (dir, mut repo) = repo_with(&["2019-10-01_0930-work"]);
+        let outside = tempfile::tempdir().unwrap();
+        let screenshot = outside.path().join("screen shot.png");
+        std::fs::write(&screenshot, "png").unwrap();
+        let copy = dir.path().join("2019-10-01_0930.attachments").join("screen shot.png");
+        repo.attach("HEAD", &screenshot, |_| Ok(true)).unwrap();
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "png");
+        let body = repo.entries[0].body(&repo.config).unwrap();
+        assert!(body.starts_with("![") && body.contains("](<2019-10-01_0930.attachments/"));
+
+        // attachments are not entries, even when named like one
+        std::fs::write(copy.with_file_name("2019-10-02_0930-note"), "").unwrap();
+        repo.collect_entries();
+        assert_eq!(repo.entries.len(), 1);
+
+        repo.retime("HEAD", "2019-10-03 09:30", |_| Ok(true)).unwrap();
+        let moved = dir.path().join("2019-10-03_0930.attachments");
+        assert!(moved.join("screen shot.png").exists());
+        let body = repo.entries[0].body(&repo.config).unwrap();
+        assert!(body.contains("](<2019-10-03_0930.attachments/"));
+
+        repo.remove_entry("HEAD", |_| Ok(true)).unwrap();
+        assert!(!moved.exists());
+    }
+
+    #[test]
+    fn links_follow_renamed_entries() {
+        let (_dir, mut repo) = repo_with(&["2019-10-01_0930-work", "2019-10-02_0930"]);
+        let id: String = repo.entries[0].id().chars().take(7).collect();
+        let text =
+            format!("[[2019-10-01_0930-work]] [[id:{}]] [[2019-10-01_0930]] [[missing]]\n", id);
+        repo.entries[1].write_body(&repo.config, &text).unwrap();
+        let targets = |repo: &JrnRepo| {
+            let text = repo.entries[1].body(&repo.config).unwrap();
+            link::parse(&text).iter().map(|l| repo.resolve(l.target)).collect::<Vec<_>>()
+        };
+        assert_eq!(targets(&repo), vec![Some(0), Some(0), Some(0), None]);
+
+        repo.push_tag("home", Some("HEAD~1"), |_| Ok(true)).unwrap();
+        assert_eq!(targets(&repo), vec![Some(0), Some(0), Some(0), None]);
+        let body = repo.entries[1].body(&repo.config).unwrap();
+        assert!(body.starts_with("[[2019-10-01_0930-work_home]]"));
+
+        repo.retime("HEAD~1", "2019-09-30 09:30", |_| Ok(true)).unwrap();
+        assert_eq!(targets(&repo), vec![Some(0), Some(0), Some(0), None]);
+        assert!(repo.entries[1].body(&repo.config).unwrap().contains("[[2019-09-30_0930]]"));
+    }
+
+    #[test]
+    fn links_by_time_leave_out_the_utc_offset() {
+        let (_dir, mut repo) =
+            repo_with(&["2019-10-01_0930UTC+0200-work", "2019-10-02_0930UTC+0200"]);
+        repo.config.set(JrnSetting::RecordUtcOffset, "true");
+        repo.entries[1].write_body(&repo.config, "[[2019-10-01_0930]]\n").unwrap();
+        assert_eq!(repo.resolve(Target::Name("2019-10-01_0930")), Some(0));
+
+        repo.retime("HEAD~1", "2019-09-30 09:30", |_| Ok(true)).unwrap();
+        assert!(repo.entries[0].file_name(&repo.config).starts_with("2019-09-30_0930UTC"));
+        assert_eq!(repo.entries[1].body(&repo.config).unwrap(), "[[2019-09-30_0930]]\n");
+        assert_eq!(repo.resolve(Target::Name("2019-09-30_0930")), Some(0));
+    }
+}
@@ -0,0 +1,339 @@
+//! Aggregates over the entries of a journal, see [Stats]
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Serialize;
+
+use super::{CountAndTag, JrnEntry, Settings};
+use crate::color::{paint, Style};
+
+static DAY_FORMAT: &str = "%Y-%m-%d";
+/// the shades of the heatmap, from no entries to four or more
+static SHADES: &[char] = &['·', '░', '▒', '▓', '█'];
+/// the longest bar drawn in the per month and week charts
+static BAR_WIDTH: usize = 40;
+static WEEKDAYS: &[&str] = &["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Counts, streaks and word counts of a set of entries
+///
+/// Days are those of the configured display time zone
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Stats {
+    pub entries: usize,
+    pub words: usize,
+    /// the first and last day written, as 2019-10-01
+    pub first: Option<String>,
+    pub last: Option<String>,
+    pub days_written: usize,
+    /// consecutive days written up to today, or up to yesterday if nothing was written today yet
+    pub current_streak: usize,
+    pub longest_streak: usize,
+    /// entries per day, as 2019-10-01, days without entries are left out
+    pub per_day: BTreeMap<String, usize>,
+    /// entries per ISO week, as 2019-W40
+    pub per_week: BTreeMap<String, usize>,
+    /// entries per month, as 2019-10
+    pub per_month: BTreeMap<String, usize>,
+    /// the most used tags, most used first
+    pub tags: Vec<CountAndTag>,
+    /// how often each location was recorded, most frequent first
+    pub locations: Vec<LocationCount>,
+}
+
+/// The number of entries written at a location
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LocationCount {
+    pub location: String,
+    pub count: usize,
+}
+
+impl Stats {
+    /// Aggregates entries, keeping the top_tags most used tags
+    ///
+    /// Entries whose text can not be read count no words
+    pub fn new<'a>(
+        entries: impl IntoIterator<Item = &'a JrnEntry>,
+        config: &Settings,
+        today: NaiveDate,
+        top_tags: usize,
+    ) -> Self {
+        let zone = config.get_display_zone();
+        let mut stats = Stats::default();
+        let mut days: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+        let mut tags: HashMap<&str, usize> = HashMap::new();
+        let mut locations: HashMap<&str, usize> = HashMap::new();
+
+        for entry in entries {
+            stats.entries += 1;
+            stats.words += match entry.body(config) {
+                Ok(body) => body.split_whitespace().count(),
+                Err(e) => {
+                    log::warn!("Can not count the words of {}: {}", entry.file_path.display(), e);
+                    0
+                }
+            };
+            *days.entry(entry.creation_time.in_zone(zone).date()).or_default() += 1;
+            for tag in &entry.tags {
+                *tags.entry(tag).or_default() += 1;
+            }
+            if let Some(location) = entry.location.as_option() {
+                *locations.entry(location).or_default() += 1;
+            }
+        }
+
+        for (day, count) in &days {
+            let week = day.iso_week();
+            let week = format!("{}-W{:02}", week.year(), week.week());
+            *stats.per_week.entry(week).or_default() += count;
+            let month = format!("{}-{:02}", day.year(), day.month());
+            *stats.per_month.entry(month).or_default() += count;
+            stats.per_day.insert(day.format(DAY_FORMAT).to_string(), *count);
+        }
+        stats.first = days.keys().next().map(|d| d.format(DAY_FORMAT).to_string());
+        stats.last = days.keys().next_back().map(|d| d.format(DAY_FORMAT).to_string());
+        stats.days_written = days.len();
+        let written: HashSet<NaiveDate> = days.keys().copied().collect();
+        stats.longest_streak = longest_streak(&written);
+        stats.current_streak = current_streak(&written, today);
+
+        stats.tags = tags
+            .into_iter()
+            .map(|(tag, count)| CountAndTag(count, String::from(tag)))
+            .collect();
+        stats.tags.sort_by(|a, b| b.cmp(a));
+        stats.tags.truncate(top_tags);
+        stats.locations = locations
+            .into_iter()
+            .map(|(location, count)| LocationCount { location: String::from(location), count })
+            .collect();
+        stats
+            .locations
+            .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.location.cmp(&b.location)));
+        stats
+    }
+
+    /// Writes a summary for reading in a terminal, ending in a heatmap of the weeks up to end
+    pub fn write_text<W: Write>(
+        &self,
+        f: &mut W,
+        end: NaiveDate,
+        weeks: usize,
+        color: bool,
+    ) -> fmt::Result {
+        let (first, last) = match (&self.first, &self.last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return writeln!(f, "no entries"),
+        };
+        let span = parse_day(last).signed_duration_since(parse_day(first)).num_days() as f64 + 1.0;
+        let per_entry = self.words / self.entries.max(1);
+
+        writeln!(f, "entries     {} from {} to {}", self.entries, first, last)?;
+        writeln!(f, "words       {}, {} per entry", self.words, per_entry)?;
+        writeln!(f, "days        {} written of {}", self.days_written, span)?;
+        writeln!(
+            f,
+            "average     {:.1} per day, {:.1} per week, {:.1} per month",
+            self.entries as f64 / span,
+            self.entries as f64 / (span / 7.0).max(1.0),
+            self.entries as f64 / (span / 30.44).max(1.0),
+        )?;
+        writeln!(
+            f,
+            "streak      {} days, longest {} days",
+            self.current_streak, self.longest_streak
+        )?;
+
+        write_chart(f, "months", &self.per_month, None, color)?;
+        write_chart(f, "weeks", &self.per_week, Some(weeks), color)?;
+
+        if !self.tags.is_empty() {
+            writeln!(f, "\ntags")?;
+            for tag in &self.tags {
+                writeln!(f, "  {:>6}  {}", tag.0, paint(&tag.1, Style::Tag, color))?;
+            }
+        }
+        if !self.locations.is_empty() {
+            writeln!(f, "\nlocations")?;
+            for location in &self.locations {
+                let name = paint(&location.location, Style::Location, color);
+                writeln!(f, "  {:>6}  {}", location.count, name)?;
+            }
+        }
+
+        writeln!(f)?;
+        write_heatmap(f, &self.per_day, end, weeks)
+    }
+}
+
+fn parse_day(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, DAY_FORMAT).unwrap_or_default()
+}
+
+fn longest_streak(written: &HashSet<NaiveDate>) -> usize {
+    written
+        .iter()
+        .filter(|day| !written.contains(&(**day - Duration::days(1))))
+        .map(|start| streak_from(written, *start, 1))
+        .max()
+        .unwrap_or(0)
+}
+
+/// The streak ending today, or yesterday as today may still be written
+fn current_streak(written: &HashSet<NaiveDate>, today: NaiveDate) -> usize {
+    let yesterday = today - Duration::days(1);
+    match (written.contains(&today), written.contains(&yesterday)) {
+        (true, _) => streak_from(written, today, -1),
+        (false, true) => streak_from(written, yesterday, -1),
+        (false, false) => 0,
+    }
+}
+
+/// Counts the consecutive days written from start, stepping step days at a time
+fn streak_from(written: &HashSet<NaiveDate>, start: NaiveDate, step: i64) -> usize {
+    let mut day = start;
+    let mut length = 0;
+    while written.contains(&day) {
+        length += 1;
+        day += Duration::days(step);
+    }
+    length
+}
+
+/// Writes counts as a bar chart, only the last entries if given
+fn write_chart<W: Write>(
+    f: &mut W,
+    title: &str,
+    counts: &BTreeMap<String, usize>,
+    last: Option<usize>,
+    color: bool,
+) -> fmt::Result {
+    let skip = last.map_or(0, |last| counts.len().saturating_sub(last));
+    let max = counts.values().skip(skip).copied().max().unwrap_or(0).max(1);
+    writeln!(f, "\n{}", title)?;
+    for (key, count) in counts.iter().skip(skip) {
+        let bar = "█".repeat((count * BAR_WIDTH).div_ceil(max));
+        writeln!(f, "  {:<8}  {:>4}  {}", key, count, paint(bar, Style::Time, color))?;
+    }
+    Ok(())
+}
+
+/// Writes a calendar of the weeks up to today, a column per week and a row per weekday,
+/// each day shaded by its number of entries
+fn write_heatmap<W: Write>(
+    f: &mut W,
+    per_day: &BTreeMap<String, usize>,
+    today: NaiveDate,
+    weeks: usize,
+) -> fmt::Result {
+    let weeks = weeks.max(1);
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let start = monday - Duration::weeks(weeks as i64 - 1);
+
+    // month names above the first column of each month
+    let mut header = String::from("     ");
+    let mut month = None;
+    for week in 0..weeks {
+        let day = start + Duration::weeks(week as i64);
+        if month != Some(day.month()) && header.chars().count() <= 5 + week * 2 {
+            month = Some(day.month());
+            header.push_str(&day.format("%b").to_string());
+        }
+        while header.chars().count() < 5 + (week + 1) * 2 {
+            header.push(' ');
+        }
+    }
+    writeln!(f, "{}", header.trim_end())?;
+
+    for (weekday, name) in WEEKDAYS.iter().enumerate() {
+        write!(f, "{:<5}", name)?;
+        for week in 0..weeks {
+            let day = start + Duration::weeks(week as i64) + Duration::days(weekday as i64);
+            if day > today {
+                break;
+            }
+            let count = per_day.get(&day.format(DAY_FORMAT).to_string()).copied().unwrap_or(0);
+            write!(f, "{} ", SHADES[count.min(SHADES.len() - 1)])?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Location, TimeStamp};
+    use std::fs;
+    use std::path::Path;
+
+    fn entry(
+        dir: &Path,
+        day: u32,
+        hour: u32,
+        body: &str,
+        tags: &[&str],
+        location: &str,
+    ) -> JrnEntry {
+        let file_path = dir.join(format!("2019-10-{:02}_{:02}00", day, hour));
+        fs::write(&file_path, body).unwrap();
+        JrnEntry {
+            creation_time: TimeStamp::from_ymdhm(2019, 10, day, hour, 0).unwrap(),
+            location: Location::from(String::from(location)),
+            tags: tags.iter().map(|t| String::from(*t)).collect(),
+            file_path,
+        }
+    }
+
+    #[test]
+    fn aggregates_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = vec![
+            entry(dir.path(), 1, 9, "one two", &["work"], "Berlin"),
+            entry(dir.path(), 1, 18, "three", &["work", "home"], "Berlin"),
+            entry(dir.path(), 2, 9, "four", &["home"], "None"),
+            entry(dir.path(), 3, 9, "", &["work"], "Paris"),
+            entry(dir.path(), 7, 9, "five six", &[], "Paris"),
+            entry(dir.path(), 8, 9, "", &[], "Berlin"),
+        ];
+        let today = NaiveDate::from_ymd_opt(2019, 10, 9).unwrap();
+        let stats = Stats::new(&entries, &Settings::default(), today, 1);
+
+        assert_eq!(stats.entries, 6);
+        assert_eq!(stats.words, 6);
+        assert_eq!(stats.first.as_deref(), Some("2019-10-01"));
+        assert_eq!(stats.days_written, 5);
+        assert_eq!(stats.longest_streak, 3);
+        assert_eq!(stats.current_streak, 2);
+        assert_eq!(stats.per_day["2019-10-01"], 2);
+        assert_eq!(stats.per_week["2019-W40"], 4);
+        assert_eq!(stats.per_week["2019-W41"], 2);
+        assert_eq!(stats.per_month["2019-10"], 6);
+        assert_eq!(stats.tags, vec![CountAndTag(3, String::from("work"))]);
+        let berlin = LocationCount { location: String::from("Berlin"), count: 3 };
+        assert_eq!(stats.locations[0], berlin);
+        assert_eq!(stats.locations.len(), 2);
+
+        let later = NaiveDate::from_ymd_opt(2019, 10, 11).unwrap();
+        assert_eq!(Stats::new(&entries, &Settings::default(), later, 1).current_streak, 0);
+    }
+
+    #[test]
+    fn draws_heatmap() {
+        let mut per_day = BTreeMap::new();
+        per_day.insert(String::from("2019-10-07"), 1);
+        per_day.insert(String::from("2019-10-08"), 9);
+        let mut out = String::new();
+        // Wednesday, 2019-10-09
+        let today = NaiveDate::from_ymd_opt(2019, 10, 9).unwrap();
+        write_heatmap(&mut out, &per_day, today, 6).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "     Sep       Oct");
+        assert_eq!(lines[1], "Mon  · · · · · ░ ");
+        assert_eq!(lines[2], "Tue  · · · · · █ ");
+        assert_eq!(lines[3], "Wed  · · · · · · ");
+        assert_eq!(lines[4], "Thu  · · · · · ");
+        assert_eq!(lines.len(), 8);
+    }
+}
@@ -0,0 +1,190 @@
+//! Helpers for hierarchical and namespaced tags
+//!
+//! Tags may be nested with '/', as in work/projectx/design,
+//! or namespaced with ':', as in person:alice
+//!
+//! Neither character may appear in a file name, so tags are encoded before being
+//! written into an entries file path. '/' is written as '+' and ':' as '=',
+//! any other character that would break parsing is percent escaped.
+//! Entries named before tags were encoded are renamed by jrn tags --migrate, see [is_legacy]
+
+/// separates the levels of a hierarchical tag
+pub static HIERARCHY_SEPARATOR: char = '/';
+/// separates the key and value of a namespaced tag
+pub static NAMESPACE_SEPARATOR: char = ':';
+
+static ENCODED_HIERARCHY_SEPARATOR: char = '+';
+static ENCODED_NAMESPACE_SEPARATOR: char = '=';
+static ESCAPE: char = '%';
+
+/// Encodes tag so it can be stored in a file name
+/// where tags are separated by deliminator
+pub fn encode(tag: &str, deliminator: char) -> String {
+    let mut encoded = String::with_capacity(tag.len());
+    for c in tag.chars() {
+        if c == HIERARCHY_SEPARATOR {
+            encoded.push(ENCODED_HIERARCHY_SEPARATOR);
+        } else if c == NAMESPACE_SEPARATOR {
+            encoded.push(ENCODED_NAMESPACE_SEPARATOR);
+        } else if needs_escape(c, deliminator) {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                encoded.push_str(&format!("{}{:02X}", ESCAPE, byte));
+            }
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+/// Reverses [encode]
+///
+/// malformed escapes are kept verbatim
+pub fn decode(encoded: &str) -> String {
+    let mut bytes: Vec<u8> = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    let raw = encoded.as_bytes();
+    while i < raw.len() {
+        let c = raw[i];
+        if c == ESCAPE as u8 {
+            let hex = encoded.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok());
+            if let Some(byte) = hex {
+                bytes.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        if c == ENCODED_HIERARCHY_SEPARATOR as u8 {
+            bytes.push(HIERARCHY_SEPARATOR as u8);
+        } else if c == ENCODED_NAMESPACE_SEPARATOR as u8 {
+            bytes.push(NAMESPACE_SEPARATOR as u8);
+        } else {
+            bytes.push(c);
+        }
+        i += 1;
+    }
+    String::from_utf8(bytes).unwrap_or_else(|_| String::from(encoded))
+}
+
+/// true if encoded can not have been written by [encode], so was written before tags were encoded
+/// and means what it says verbatim
+///
+/// Such as c++, which would decode to c// with its empty levels, or 50% with its lone escape.
+/// Encoded tags such as work+projectx or 50%25 are never legacy
+pub fn is_legacy(encoded: &str, deliminator: char) -> bool {
+    let tag = decode(encoded);
+    encode(&tag, deliminator) != encoded || tag.split(HIERARCHY_SEPARATOR).any(str::is_empty)
+}
+
+/// The name of tag once old is renamed to new, descendants of old are renamed along
+///
+/// Returns None if tag is neither old nor one of its descendants
+///     rename("work/projectx", "work", "job") == Some("job/projectx")
+pub fn rename(tag: &str, old: &str, new: &str) -> Option<String> {
+    let rest = tag.strip_prefix(old)?;
+    match rest.chars().next() {
+        None => Some(String::from(new)),
+        Some(c) if c == HIERARCHY_SEPARATOR => Some(format!("{}{}", new, rest)),
+        Some(_) => None,
+    }
+}
+
+fn needs_escape(c: char, deliminator: char) -> bool {
+    c == deliminator
+        || c == ESCAPE
+        || c == ENCODED_HIERARCHY_SEPARATOR
+        || c == ENCODED_NAMESPACE_SEPARATOR
+        || c == '\\'
+        || c.is_control()
+}
+
+/// Returns true if tag satisfies query
+///
+/// A query matches the tag itself, any descendant in its hierarchy
+/// and, when the query is a bare namespace key, every tag in that namespace
+/// for example
+///     work         matches work, work/projectx, work/projectx/design
+///     person       matches person:alice, person:bob
+///     person:alice matches person:alice only
+pub fn matches(tag: &str, query: &str) -> bool {
+    if tag == query {
+        return true;
+    }
+    if let Some(rest) = tag.strip_prefix(query) {
+        let next = rest.chars().next();
+        return next == Some(HIERARCHY_SEPARATOR)
+            || (next == Some(NAMESPACE_SEPARATOR) && !query.contains(NAMESPACE_SEPARATOR));
+    }
+    false
+}
+
+/// Splits a tag into the levels of its hierarchy
+pub fn levels(tag: &str) -> impl Iterator<Item = &str> {
+    tag.split(HIERARCHY_SEPARATOR).filter(|level| !level.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renames_descendants() {
+        assert_eq!(rename("work", "work", "job"), Some(String::from("job")));
+        assert_eq!(rename("work/projectx", "work", "job"), Some(String::from("job/projectx")));
+        assert_eq!(rename("workout", "work", "job"), None);
+        assert_eq!(rename("person:alice", "person", "who"), None);
+    }
+
+    #[test]
+    fn encodes_hierarchy_and_namespace() {
+        assert_eq!(encode("work/projectx/design", '_'), "work+projectx+design");
+        assert_eq!(encode("person:alice", '_'), "person=alice");
+    }
+
+    #[test]
+    fn escapes_deliminator() {
+        assert_eq!(encode("to_do", '_'), "to%5Fdo");
+        assert_eq!(decode("to%5Fdo"), "to_do");
+    }
+
+    #[test]
+    fn round_trips() {
+        for tag in &["work/projectx", "person:alice", "a+b=c%d", "mood:good/very", "ünï_côdé"] {
+            assert_eq!(&decode(&encode(tag, '_')), tag);
+        }
+    }
+
+    #[test]
+    fn recognises_legacy_tags() {
+        for legacy in &["c++", "work+", "50%", "a:b", "to\\do"] {
+            assert!(is_legacy(legacy, '_'), "{}", legacy);
+        }
+        for encoded in &["work+projectx", "person=alice", "50%25", "c%2B%2B", "plain"] {
+            assert!(!is_legacy(encoded, '_'), "{}", encoded);
+        }
+    }
+
+    #[test]
+    fn decode_keeps_malformed_escape() {
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn matches_descendants() {
+        assert!(matches("work", "work"));
+        assert!(matches("work/projectx/design", "work"));
+        assert!(matches("work/projectx/design", "work/projectx"));
+        assert!(!matches("workshop", "work"));
+        assert!(!matches("work", "work/projectx"));
+    }
+
+    #[test]
+    fn matches_namespace() {
+        assert!(matches("person:alice", "person"));
+        assert!(matches("person:alice", "person:alice"));
+        assert!(!matches("person:alice", "person:al"));
+        assert!(!matches("person:alice:x", "person:alice"));
+    }
+}
@@ -1,13 +1,19 @@
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Default)]
+use crate::tag;
+
+/// Counts the tags in a repository along with the entries referencing them
+///
+/// A tag is dropped from the container once its last reference is removed
+#[derive(Debug, Default, PartialEq)]
 pub struct TagContainer {
-    inner: HashMap<String, u16>,
+    inner: HashMap<String, Vec<PathBuf>>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
-pub struct CountAndTag(pub u16, pub String);
+pub struct CountAndTag(pub usize, pub String);
 
 impl Ord for CountAndTag {
     fn cmp(&self, other: &Self) -> Ordering {
@@ -23,38 +29,112 @@ impl PartialOrd for CountAndTag {
     }
 }
 
+/// A level of the tag hierarchy
+#[derive(Debug, Default, PartialEq)]
+pub struct TagNode {
+    /// the name of this level, design in work/projectx/design
+    pub name: String,
+    /// the full tag, work/projectx/design
+    pub tag: String,
+    /// number of entries tagged with exactly this tag
+    pub count: usize,
+    /// number of distinct entries tagged with this tag or any of its descendants
+    pub total: usize,
+    /// sorted by total, most used first
+    pub children: Vec<TagNode>,
+}
+
+// intermediate trie used to build TagNodes
+#[derive(Default)]
+struct TreeBuilder<'a> {
+    count: usize,
+    entries: BTreeSet<&'a Path>,
+    children: BTreeMap<&'a str, TreeBuilder<'a>>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn build(self, name: &str, parent: Option<&str>) -> TagNode {
+        let tag = match parent {
+            Some(parent) => format!("{}{}{}", parent, tag::HIERARCHY_SEPARATOR, name),
+            None => String::from(name),
+        };
+        TagNode {
+            name: String::from(name),
+            count: self.count,
+            total: self.entries.len(),
+            children: TreeBuilder::build_children(self.children, Some(&tag)),
+            tag,
+        }
+    }
+
+    fn build_children(children: BTreeMap<&str, TreeBuilder>, parent: Option<&str>) -> Vec<TagNode> {
+        let mut nodes: Vec<TagNode> = children
+            .into_iter()
+            .map(|(name, builder)| builder.build(name, parent))
+            .collect();
+        nodes.sort_by(|a, b| b.total.cmp(&a.total).then(a.name.cmp(&b.name)));
+        nodes
+    }
+}
+
 impl TagContainer {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn insert(&mut self, tag: &str) {
-        if let Some(n) = self.inner.get_mut(tag) {
-            *n += 1;
+    /// Increments the count of tag, recording entry as referencing it
+    pub fn insert(&mut self, tag: &str, entry: &Path) {
+        if let Some(entries) = self.inner.get_mut(tag) {
+            entries.push(entry.to_path_buf());
         } else {
-            let s = String::from(tag);
-            self.inner.insert(s, 1);
+            self.inner.insert(String::from(tag), vec![entry.to_path_buf()]);
         }
     }
 
-    pub fn remove(&mut self, tag: &str) {
-        if let Some(num) = self.inner.get_mut(tag) {
-            if *num > 1 {
-                *num -= 1;
+    /// Decrements the count of tag, forgetting one reference from entry
+    ///
+    /// The tag is removed when its count reaches zero
+    /// Does nothing if entry does not reference tag
+    pub fn remove(&mut self, tag: &str, entry: &Path) {
+        if let Some(entries) = self.inner.get_mut(tag) {
+            if let Some(i) = entries.iter().position(|p| p == entry) {
+                entries.remove(i);
+            }
+            if entries.is_empty() {
+                self.inner.remove(tag);
             }
-        } else {
-            self.inner.remove(tag);
         }
     }
 
-    pub fn count(&self, tag: &str) -> Option<&u16> {
-        self.inner.get(tag)
+    pub fn count(&self, tag: &str) -> Option<usize> {
+        self.inner.get(tag).map(Vec::len)
+    }
+
+    /// The paths of all entries referencing tag
+    pub fn entries(&self, tag: &str) -> Option<&[PathBuf]> {
+        self.inner.get(tag).map(Vec::as_slice)
+    }
+
+    /// Arranges the tags accepted by filter into their hierarchy
+    ///
+    /// Counts are rolled up so each node also counts the entries of its descendants
+    pub fn tree<F: Fn(&str) -> bool>(&self, filter: F) -> Vec<TagNode> {
+        let mut root: TreeBuilder = TreeBuilder::default();
+        for (t, entries) in self.inner.iter().filter(|(t, _)| filter(t)) {
+            let mut node = &mut root;
+            for level in tag::levels(t) {
+                node = node.children.entry(level).or_default();
+                node.entries.extend(entries.iter().map(PathBuf::as_path));
+            }
+            node.count += entries.len();
+        }
+        TreeBuilder::build_children(root.children, None)
     }
 
     pub fn sorted(&self) -> Vec<CountAndTag> {
         let mut heap: BinaryHeap<CountAndTag> = BinaryHeap::new();
-        for (key, count) in self.inner.iter() {
-            let item = CountAndTag(*count, String::from(key));
+        for (key, entries) in self.inner.iter() {
+            let item = CountAndTag(entries.len(), String::from(key));
             heap.push(item);
         }
         let mut vec = heap.into_sorted_vec();
@@ -70,18 +150,71 @@ mod test {
     #[test]
     fn insert_dup() {
         let mut con = TagContainer::new();
-        con.insert(&"test");
-        con.insert(&"test");
-        assert_eq!(con.count(&"test"), Some(&2));
+        con.insert("test", Path::new("a"));
+        con.insert("test", Path::new("b"));
+        assert_eq!(con.count("test"), Some(2));
+    }
+
+    #[test]
+    fn remove_last_reference_drops_tag() {
+        let mut con = TagContainer::new();
+        con.insert("test", Path::new("a"));
+        con.remove("test", Path::new("a"));
+        assert_eq!(con.count("test"), None);
+        assert!(con.sorted().is_empty());
+    }
+
+    #[test]
+    fn remove_decrements() {
+        let mut con = TagContainer::new();
+        con.insert("test", Path::new("a"));
+        con.insert("test", Path::new("b"));
+        con.remove("test", Path::new("a"));
+        assert_eq!(con.count("test"), Some(1));
+        assert_eq!(con.entries("test"), Some(&[PathBuf::from("b")][..]));
+    }
+
+    #[test]
+    fn remove_unreferenced_is_noop() {
+        let mut con = TagContainer::new();
+        con.insert("test", Path::new("a"));
+        con.remove("test", Path::new("b"));
+        con.remove("other", Path::new("a"));
+        assert_eq!(con.count("test"), Some(1));
+    }
+
+    #[test]
+    fn builds_tree() {
+        let mut con = TagContainer::new();
+        con.insert("work", Path::new("a"));
+        con.insert("work/projectx", Path::new("a"));
+        con.insert("work/projectx/design", Path::new("b"));
+        con.insert("home", Path::new("c"));
+        let tree = con.tree(|_| true);
+
+        assert_eq!(tree.len(), 2);
+        let work = &tree[0];
+        assert_eq!((work.name.as_str(), work.count, work.total), ("work", 1, 2));
+        let projectx = &work.children[0];
+        assert_eq!(
+            (projectx.tag.as_str(), projectx.count, projectx.total),
+            ("work/projectx", 1, 2)
+        );
+        let design = &projectx.children[0];
+        assert_eq!(
+            (design.tag.as_str(), design.count, design.total),
+            ("work/projectx/design", 1, 1)
+        );
+        assert_eq!(tree[1].tag, "home");
     }
 
     #[test]
     fn can_sort() {
         let mut con = TagContainer::new();
-        con.insert(&"A");
-        con.insert(&"B");
-        con.insert(&"B");
-        con.insert(&"C");
+        con.insert("A", Path::new("a"));
+        con.insert("B", Path::new("a"));
+        con.insert("B", Path::new("b"));
+        con.insert("C", Path::new("c"));
         for (i, ct) in con.sorted().iter().enumerate() {
             match i {
                 0 => assert_eq!(ct.1, "B"),
@@ -1,31 +1,442 @@
 use chrono::prelude::*;
-use serde::export::fmt::Error;
-use serde::export::Formatter;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::fmt::{self, Formatter};
 
+use crate::JrnError;
+
+/// the original minute resolution format, always accepted when reading entries
 static TIMESTAMP_FMT: &str = "%Y-%m-%d_%H%M";
+/// precedes the utc offset of a timestamp, 2019-10-01_0930UTC+0200
+static OFFSET_PREFIX: &str = "UTC";
+static OFFSET_FMT: &str = "%z";
+static OFFSET_RE: &str = r"(?:UTC(?P<offset>[+-]\d{4}))?";
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+/// The wall clock time an entry was written at
+///
+/// The utc offset of the writer is optional, timestamps without one
+/// are taken to be in the current local time zone
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct TimeStamp {
     inner: NaiveDateTime,
+    offset: Option<FixedOffset>,
+}
+
+/// Which time zone timestamps are displayed in
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DisplayZone {
+    /// the zone the timestamp was recorded in
+    Original,
+    /// the current local time zone
+    Local,
 }
 
 impl TimeStamp {
+    /// The current local time, recording the local utc offset
     pub fn now() -> Self {
         let dt: DateTime<Local> = Local::now();
-        let ndt: NaiveDateTime = dt.naive_local();
-        TimeStamp { inner: ndt }
+        TimeStamp {
+            inner: dt.naive_local(),
+            offset: Some(*dt.offset()),
+        }
+    }
+
+    /// Returns None if the arguments do not form a valid date and time
+    pub fn from_ymdhm(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Option<Self> {
+        let date = NaiveDate::from_ymd_opt(year, month, day)?;
+        let ndt = date.and_hms_opt(hour, minute, 0)?;
+        Some(TimeStamp { inner: ndt, offset: None })
+    }
+
+    /// Formats the wall clock time with a strftime pattern, leaving out the offset
+    pub fn format(self, pattern: &str) -> String {
+        self.inner.format(pattern).to_string()
+    }
+
+    /// The calendar date of the wall clock time
+    pub fn date(self) -> NaiveDate {
+        self.inner.date()
+    }
+
+    /// Formats as ISO 8601, 2019-10-01T09:30:00+02:00
+    /// the offset is left out if none was recorded
+    pub fn to_iso8601(self) -> String {
+        let time = self.inner.format("%Y-%m-%dT%H:%M:%S").to_string();
+        match self.offset {
+            Some(offset) => format!("{}{}", time, offset),
+            None => time,
+        }
+    }
+
+    /// A wall clock time without a recorded offset
+    pub fn from_naive(inner: NaiveDateTime) -> Self {
+        TimeStamp { inner, offset: None }
+    }
+
+    /// Records the utc offset this timestamp was written with
+    pub fn with_offset(self, offset: FixedOffset) -> Self {
+        TimeStamp {
+            offset: Some(offset),
+            ..self
+        }
+    }
+
+    /// The same timestamp the given number of minutes later, keeping its offset
+    pub fn plus_minutes(self, minutes: i64) -> Self {
+        TimeStamp {
+            inner: self.inner + chrono::Duration::minutes(minutes),
+            ..self
+        }
+    }
+
+    /// Forgets the utc offset, keeping the wall clock time
+    pub fn without_offset(self) -> Self {
+        TimeStamp {
+            offset: None,
+            ..self
+        }
+    }
+
+    /// The absolute time of this timestamp
+    ///
+    /// If no offset was recorded the timestamp is taken to be local time
+    pub fn to_utc(self) -> DateTime<Utc> {
+        let offset = self.offset.unwrap_or_else(|| local_offset(&self.inner));
+        Utc.from_utc_datetime(&(self.inner - offset))
+    }
+
+    /// Converts this timestamp to the given zone for display
+    ///
+    /// Timestamps without an offset are already local and are left as they are
+    pub fn in_zone(&self, zone: DisplayZone) -> Self {
+        match (zone, self.offset) {
+            (DisplayZone::Local, Some(_)) => {
+                let local = self.to_utc().with_timezone(&Local);
+                TimeStamp {
+                    inner: local.naive_local(),
+                    offset: Some(*local.offset()),
+                }
+            }
+            _ => *self,
+        }
+    }
+
+    /// Parses a time given on the command line, relative to now
+    ///
+    /// Accepts a date and time such as 2019-10-01 21:30, 2019-10-01T21:30 or 2019-10-01 9pm,
+    /// a day relative to now such as today, yesterday, tomorrow or 3 days ago (or weeks ago),
+    /// optionally followed by a time, or a time alone which refers to today.
+    /// Times may be written 21:30, 2130, 9pm, 9:30am, noon or midnight.
+    /// A relative day without a time keeps the current time of day,
+    /// a date without a time is midnight
+    ///
+    /// The result is in the local time zone at that time
+    pub fn parse_when(input: &str, now: DateTime<Local>) -> Option<Self> {
+        let mut input = input.trim().to_lowercase();
+        let today = now.date_naive();
+
+        // separate the date and time of 2019-10-01T21:30
+        if input.len() > 10 && input.is_char_boundary(10) && input[10..].starts_with('t') {
+            input.replace_range(10..11, " ");
+        }
+        let mut words = input.splitn(2, char::is_whitespace);
+        let first = words.next()?;
+        let mut rest = words.next().unwrap_or("").trim();
+
+        // 3 days ago 08:00
+        if let Ok(n) = first.parse::<i64>() {
+            let mut ago = rest.splitn(3, char::is_whitespace);
+            if let (Some(unit), Some("ago")) = (ago.next(), ago.next()) {
+                let days = match unit {
+                    "day" | "days" => n,
+                    "week" | "weeks" => n.checked_mul(7)?,
+                    _ => return None,
+                };
+                let date = today.checked_sub_signed(chrono::Duration::days(days))?;
+                rest = ago.next().unwrap_or("").trim();
+                let time = if rest.is_empty() { now.time() } else { parse_time(rest)? };
+                return TimeStamp::local(date.and_time(time));
+            }
+        }
+
+        let (date, default_time) = match first {
+            "now" if rest.is_empty() => (today, Some(now.time())),
+            "today" => (today, Some(now.time())),
+            "yesterday" => (today.pred_opt()?, Some(now.time())),
+            "tomorrow" => (today.succ_opt()?, Some(now.time())),
+            _ => match NaiveDate::parse_from_str(first, "%Y-%m-%d") {
+                Ok(date) => (date, Some(NaiveTime::MIN)),
+                // no date given, the whole input is a time today
+                Err(_) => return TimeStamp::local(today.and_time(parse_time(&input)?)),
+            },
+        };
+        let time = if rest.is_empty() {
+            default_time?
+        } else {
+            parse_time(rest)?
+        };
+        TimeStamp::local(date.and_time(time))
+    }
+
+    /// The local wall clock time local, recording the local utc offset
+    fn local(local: NaiveDateTime) -> Option<Self> {
+        let dt = Local.from_local_datetime(&local).earliest()?;
+        Some(TimeStamp {
+            inner: dt.naive_local(),
+            offset: Some(*dt.offset()),
+        })
+    }
+
+    /// Parses the first timestamp found in s, returning it and the remainder of s
+    ///
+    /// Returns None if s contains no valid timestamp
+    pub fn parse_prefix(s: &str) -> Option<(Self, &str)> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(
+                r"(?x)
+            (?P<year>\d{4})
+            -
+            (?P<month>\d{2})
+            -
+            (?P<day>\d{2})
+            _
+            (?P<hr>\d{2})
+            (?P<min>\d{2})
+            (?:UTC(?P<offset>[+-]\d{4}))?
+            "
+            )
+            .unwrap();
+        };
+
+        let captures = RE.captures(s)?;
+        let num = |name: &str| captures.name(name).unwrap().as_str().parse::<u32>().ok();
+        let mut stamp = TimeStamp::from_ymdhm(
+            num("year")? as i32,
+            num("month")?,
+            num("day")?,
+            num("hr")?,
+            num("min")?,
+        )?;
+        if let Some(m) = captures.name("offset") {
+            stamp = stamp.with_offset(parse_offset(m.as_str())?);
+        }
+        let rest = &s[captures.get(0).unwrap().end()..];
+        Some((stamp, rest))
+    }
+}
+
+/// How timestamps are written into and read from file names
+///
+/// Either one of the presets
+///     minutes   2019-10-01_0930, the default
+///     seconds   2019-10-01_093015
+///     iso-week  2019-W40-2_0930
+/// or a custom strftime pattern such as %Y%m%dT%H%M%S
+///
+/// Names written with the original minute resolution format are always accepted
+#[derive(Debug, Clone)]
+pub struct TimestampFormat {
+    pattern: String,
+    regex: Regex,
+}
+
+impl PartialEq for TimestampFormat {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Default for TimestampFormat {
+    fn default() -> Self {
+        TimestampFormat::new("minutes").unwrap()
+    }
+}
+
+impl TimestampFormat {
+    /// Builds the format from a preset name or strftime pattern
+    ///
+    /// Returns Err if the pattern contains specifiers that can not be read back
+    /// or characters that are not allowed in file names
+    pub fn new(format: &str) -> Result<Self, JrnError> {
+        let pattern = match format.trim() {
+            "minutes" => TIMESTAMP_FMT,
+            "seconds" => "%Y-%m-%d_%H%M%S",
+            "iso-week" => "%G-W%V-%u_%H%M",
+            custom => custom,
+        };
+        let regex = Regex::new(&format!("(?P<stamp>{}){}", pattern_to_regex(pattern)?, OFFSET_RE))?;
+        Ok(TimestampFormat {
+            pattern: String::from(pattern),
+            regex,
+        })
+    }
+
+    /// Formats t as it is written in file names
+    pub fn format(&self, t: &TimeStamp) -> String {
+        format_with(t, &self.pattern)
+    }
+
+    /// Formats the wall clock time of t, leaving out the utc offset even if recorded
+    pub fn format_wall_clock(&self, t: &TimeStamp) -> String {
+        t.format(&self.pattern)
+    }
+
+    /// Drops any precision from t that this format does not write
+    pub fn truncate(&self, t: TimeStamp) -> TimeStamp {
+        match self.parse_configured(&self.format(&t)) {
+            Some((truncated, _)) => truncated,
+            None => t,
+        }
+    }
+
+    /// Parses the first timestamp found in s, returning it and the remainder of s
+    ///
+    /// Falls back to the original minute resolution format
+    pub fn parse_prefix<'a>(&self, s: &'a str) -> Option<(TimeStamp, &'a str)> {
+        self.parse_configured(s).or_else(|| {
+            if self.pattern == TIMESTAMP_FMT {
+                None
+            } else {
+                TimeStamp::parse_prefix(s)
+            }
+        })
+    }
+
+    fn parse_configured<'a>(&self, s: &'a str) -> Option<(TimeStamp, &'a str)> {
+        let captures = self.regex.captures(s)?;
+        let stamp = captures.name("stamp")?.as_str();
+        // formats without a time of day refer to midnight
+        let inner = NaiveDateTime::parse_from_str(stamp, &self.pattern)
+            .ok()
+            .or_else(|| {
+                let date = NaiveDate::parse_from_str(stamp, &self.pattern).ok()?;
+                Some(date.and_time(NaiveTime::MIN))
+            })?;
+        let mut t = TimeStamp { inner, offset: None };
+        if let Some(m) = captures.name("offset") {
+            t = t.with_offset(parse_offset(m.as_str())?);
+        }
+        let rest = &s[captures.get(0)?.end()..];
+        Some((t, rest))
+    }
+}
+
+/// formats t with pattern, followed by its utc offset if recorded
+fn format_with(t: &TimeStamp, pattern: &str) -> String {
+    let mut s = t.inner.format(pattern).to_string();
+    if let Some(offset) = t.offset {
+        let dt = offset.from_utc_datetime(&(t.inner - offset));
+        s.push_str(OFFSET_PREFIX);
+        s.push_str(&dt.format(OFFSET_FMT).to_string());
+    }
+    s
+}
+
+/// Translates a strftime pattern into a regex matching its output
+fn pattern_to_regex(pattern: &str) -> Result<String, JrnError> {
+    let invalid = || JrnError::InvalidTimestampFormat(String::from(pattern));
+    let mut re = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '/' || c == '\\' || c.is_control() {
+            return Err(invalid());
+        }
+        if c != '%' {
+            re.push_str(&regex::escape(&c.to_string()));
+            continue;
+        }
+        let part = match chars.next().ok_or_else(invalid)? {
+            'Y' | 'G' => r"\d{4}",
+            'C' | 'y' | 'g' | 'm' | 'd' | 'H' | 'I' | 'M' | 'S' | 'U' | 'W' | 'V' => r"\d{2}",
+            'j' => r"\d{3}",
+            'u' | 'w' => r"\d",
+            'e' | 'k' | 'l' => r"[ \d]\d",
+            'b' | 'h' | 'a' => r"[A-Za-z]{3}",
+            'B' | 'A' => r"[A-Za-z]+",
+            'p' | 'P' => r"[AaPp][Mm]",
+            'F' => r"\d{4}-\d{2}-\d{2}",
+            'R' => r"\d{2}:\d{2}",
+            'T' => r"\d{2}:\d{2}:\d{2}",
+            's' => r"\d+",
+            '%' => "%",
+            _ => return Err(invalid()),
+        };
+        re.push_str(part);
+    }
+    Ok(re)
+}
+
+/// parses a time of day as 21:30, 2130, 9pm, 9:30 am, noon or midnight
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    let s = s.trim();
+    match s {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return Some(NaiveTime::MIN),
+        _ => {}
+    }
+
+    let (clock, meridiem) = if let Some(clock) = s.strip_suffix("am") {
+        (clock.trim(), Some(0))
+    } else if let Some(clock) = s.strip_suffix("pm") {
+        (clock.trim(), Some(12))
+    } else {
+        (s, None)
+    };
+
+    let (hour, minute): (u32, u32) = if let Some(i) = clock.find(':') {
+        (clock[..i].parse().ok()?, clock[i + 1..].parse().ok()?)
+    } else if clock.len() == 4 && meridiem.is_none() {
+        (clock[..2].parse().ok()?, clock[2..].parse().ok()?)
+    } else {
+        (clock.parse().ok()?, 0)
+    };
+
+    let hour = match meridiem {
+        Some(_) if hour == 0 || hour > 12 => return None,
+        Some(add) => hour % 12 + add,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// parses an offset formatted as +hhmm or -hhmm
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let sign = if s.starts_with('-') { -1 } else { 1 };
+    let hours: i32 = s.get(1..3)?.parse().ok()?;
+    let minutes: i32 = s.get(3..5)?.parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// the offset of the local time zone at the given local time
+fn local_offset(local: &NaiveDateTime) -> FixedOffset {
+    Local
+        .offset_from_local_datetime(local)
+        .earliest()
+        .unwrap_or_else(|| Local.offset_from_utc_datetime(local))
+}
+
+/// Timestamps are ordered by the absolute time they refer to
+impl Ord for TimeStamp {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let offset_secs = |t: &TimeStamp| t.offset.map(|o| o.local_minus_utc());
+        self.to_utc()
+            .cmp(&other.to_utc())
+            .then(self.inner.cmp(&other.inner))
+            .then(offset_secs(self).cmp(&offset_secs(other)))
     }
+}
 
-    pub fn from_ymdhm(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Self {
-        let date = NaiveDate::from_ymd(year, month, day);
-        let ndt = date.and_hms(hour, minute, 0);
-        TimeStamp { inner: ndt }
+impl PartialOrd for TimeStamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
+/// Formats the timestamp in the original minute resolution format
 impl std::fmt::Display for TimeStamp {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(f, "{}", self.inner.format(TIMESTAMP_FMT))
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_with(self, TIMESTAMP_FMT))
     }
 }
 
@@ -35,12 +446,153 @@ mod test {
     use std::fs::File;
     use std::path::Path;
 
+    fn offset(hours: i32) -> FixedOffset {
+        FixedOffset::east_opt(hours * 3600).unwrap()
+    }
+
     #[test]
     fn can_write_timestamp() {
         let timestamp = TimeStamp::now().to_string();
         let path = Path::new(&timestamp);
-        File::create(&path).unwrap();
+        File::create(path).unwrap();
         assert!(&path.exists());
-        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn formats_offset() {
+        let t = TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap();
+        assert_eq!(t.to_string(), "2019-10-01_0930");
+        assert_eq!(t.with_offset(offset(2)).to_string(), "2019-10-01_0930UTC+0200");
+        assert_eq!(t.with_offset(offset(-5)).to_string(), "2019-10-01_0930UTC-0500");
+    }
+
+    #[test]
+    fn parses_what_it_writes() {
+        let t = TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap();
+        let west = FixedOffset::west_opt(5 * 3600 + 1800).unwrap();
+        for t in &[t, t.with_offset(offset(2)), t.with_offset(west)] {
+            let s = format!("{}-tag", t);
+            assert_eq!(TimeStamp::parse_prefix(&s), Some((*t, "-tag")));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        assert_eq!(TimeStamp::parse_prefix("2019-13-01_0930"), None);
+        assert_eq!(TimeStamp::parse_prefix("2019-02-30_0930"), None);
+        assert_eq!(TimeStamp::parse_prefix("2019-02-01_2530"), None);
+    }
+
+    #[test]
+    fn formats_presets() {
+        let t = TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap();
+        let format = |f: &str| TimestampFormat::new(f).unwrap().format(&t);
+        assert_eq!(format("minutes"), "2019-10-01_0930");
+        assert_eq!(format("seconds"), "2019-10-01_093000");
+        assert_eq!(format("iso-week"), "2019-W40-2_0930");
+        assert_eq!(format("%Y%m%dT%H%M"), "20191001T0930");
+    }
+
+    #[test]
+    fn parses_configured_formats() {
+        let t = TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap().with_offset(offset(2));
+        for f in &["minutes", "seconds", "iso-week", "%Y%m%dT%H%M", "%d.%b.%Y"] {
+            let format = TimestampFormat::new(f).unwrap();
+            let s = format!("{}-tag", format.format(&t));
+            let (parsed, rest) = format.parse_prefix(&s).unwrap();
+            assert_eq!(format.format(&parsed), format.format(&t), "{}", f);
+            assert_eq!(rest, "-tag");
+        }
+    }
+
+    #[test]
+    fn parses_seconds() {
+        let format = TimestampFormat::new("seconds").unwrap();
+        let (parsed, _) = format.parse_prefix("2019-10-01_093015").unwrap();
+        let day = NaiveDate::from_ymd_opt(2019, 10, 1).unwrap();
+        assert_eq!(parsed.inner, day.and_hms_opt(9, 30, 15).unwrap());
+    }
+
+    #[test]
+    fn accepts_legacy_names() {
+        let format = TimestampFormat::new("iso-week").unwrap();
+        let (parsed, rest) = format.parse_prefix("2019-10-01_0930-tag").unwrap();
+        assert_eq!(parsed, TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap());
+        assert_eq!(rest, "-tag");
+    }
+
+    #[test]
+    fn rejects_unreadable_formats() {
+        assert!(TimestampFormat::new("%Y/%m/%d").is_err());
+        assert!(TimestampFormat::new("%Y-%m-%d_%Q").is_err());
+        assert!(TimestampFormat::new("%Y-%").is_err());
+    }
+
+    #[test]
+    fn truncates_to_format_precision() {
+        let format = TimestampFormat::new("minutes").unwrap();
+        let seconds = TimestampFormat::new("seconds").unwrap();
+        let (t, _) = seconds.parse_prefix("2019-10-01_093015").unwrap();
+        assert_eq!(format.truncate(t), TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap());
+    }
+
+    fn at(input: &str) -> Option<NaiveDateTime> {
+        let now = NaiveDate::from_ymd_opt(2019, 10, 17).unwrap().and_hms_opt(15, 45, 0).unwrap();
+        let now = Local.from_local_datetime(&now);
+        TimeStamp::parse_when(input, now.earliest().unwrap()).map(|t| t.inner)
+    }
+
+    fn ymdhm(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, 0)
+    }
+
+    #[test]
+    fn parses_absolute_when() {
+        assert_eq!(at("2019-10-01 21:30"), ymdhm(2019, 10, 1, 21, 30));
+        assert_eq!(at("2019-10-01T21:30"), ymdhm(2019, 10, 1, 21, 30));
+        assert_eq!(at("2019-10-01 2130"), ymdhm(2019, 10, 1, 21, 30));
+        assert_eq!(at("2019-10-01 9pm"), ymdhm(2019, 10, 1, 21, 0));
+        assert_eq!(at("2019-10-01"), ymdhm(2019, 10, 1, 0, 0));
+    }
+
+    #[test]
+    fn parses_relative_when() {
+        assert_eq!(at("now"), ymdhm(2019, 10, 17, 15, 45));
+        assert_eq!(at("yesterday"), ymdhm(2019, 10, 16, 15, 45));
+        assert_eq!(at("yesterday 9pm"), ymdhm(2019, 10, 16, 21, 0));
+        assert_eq!(at("Tomorrow 9:30 AM"), ymdhm(2019, 10, 18, 9, 30));
+        assert_eq!(at("today noon"), ymdhm(2019, 10, 17, 12, 0));
+        assert_eq!(at("12am"), ymdhm(2019, 10, 17, 0, 0));
+        assert_eq!(at("21:30"), ymdhm(2019, 10, 17, 21, 30));
+        assert_eq!(at("3 days ago 08:00"), ymdhm(2019, 10, 14, 8, 0));
+        assert_eq!(at("1 week ago"), ymdhm(2019, 10, 10, 15, 45));
+        assert_eq!(at("2130"), ymdhm(2019, 10, 17, 21, 30));
+    }
+
+    #[test]
+    fn rejects_invalid_when() {
+        assert_eq!(at("someday"), None);
+        assert_eq!(at("yesterday 13pm"), None);
+        assert_eq!(at("2019-10-01 25:00"), None);
+        assert_eq!(at("2019-13-01"), None);
+        assert_eq!(at("3 fortnights ago"), None);
+    }
+
+    #[test]
+    fn sorts_by_absolute_time() {
+        // 09:30 in Berlin happens before 08:00 in New York
+        let berlin = TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap().with_offset(offset(2));
+        let new_york = TimeStamp::from_ymdhm(2019, 10, 1, 8, 0).unwrap().with_offset(offset(-4));
+        assert!(berlin < new_york);
+        assert_eq!(berlin.to_utc(), Utc.with_ymd_and_hms(2019, 10, 1, 7, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn converts_to_local_zone() {
+        let t = TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap().with_offset(offset(2));
+        let local = t.in_zone(DisplayZone::Local);
+        assert_eq!(local.to_utc(), t.to_utc());
+        assert_eq!(t.in_zone(DisplayZone::Original), t);
     }
 }
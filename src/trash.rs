@@ -0,0 +1,183 @@
+//! Removed entries are moved to the trash of their repo rather than deleted
+//!
+//! Each removed entry gets a directory of its own in .jrn/trash, named after its id,
+//! holding its file and attachments under their original names and a record of where it came from
+//!     .jrn/trash/19a77b28cb068aaa/2019-10-01_0930-work
+//!     .jrn/trash/19a77b28cb068aaa/2019-10-01_0930.attachments/
+//!     .jrn/trash/19a77b28cb068aaa/trashed.json
+//!         {"path":"2019-10-01_0930-work","time":"2019-10-02T21:00:00+02:00"}
+//! The original path is relative to the root of the repo
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, FixedOffset, Local};
+use serde::{Deserialize, Serialize};
+
+use super::{JrnEntry, JrnError, Settings};
+use crate::config::JRN_DIR;
+use crate::entry::{free_path, rename_no_clobber};
+
+static TRASH_DIR: &str = "trash";
+static ORIGIN_FILE_NAME: &str = "trashed.json";
+
+/// Where a trashed entry came from and when it was removed
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Origin {
+    path: PathBuf,
+    /// in rfc 3339
+    time: String,
+}
+
+/// An entry in the trash
+#[derive(Debug, PartialEq)]
+pub struct Item {
+    /// the name of its directory in the trash
+    pub id: String,
+    pub dir: PathBuf,
+    /// the original path of the entry, relative to the root of the repo
+    pub path: PathBuf,
+    /// when the entry was removed
+    pub time: DateTime<FixedOffset>,
+}
+
+/// The trash of the repo at root
+pub(crate) fn dir(root: &Path) -> PathBuf {
+    root.join(JRN_DIR).join(TRASH_DIR)
+}
+
+/// Moves entry into a new directory in the trash of the repo at root, returning that directory
+pub(crate) fn put(root: &Path, entry: &JrnEntry, config: &Settings) -> Result<PathBuf, JrnError> {
+    let item = free_path(&dir(root).join(entry.id()));
+    fs::create_dir_all(&item)?;
+    let origin = Origin {
+        path: entry.file_path.strip_prefix(root).unwrap_or(&entry.file_path).to_path_buf(),
+        time: Local::now().to_rfc3339(),
+    };
+    let moved = fs::write(item.join(ORIGIN_FILE_NAME), serde_json::to_vec(&origin)?)
+        .and_then(|_| entry.move_into(&item, config));
+    if let Err(e) = moved {
+        let _ = fs::remove_dir_all(&item);
+        return Err(e.into());
+    }
+    Ok(item)
+}
+
+/// Every entry in the trash of the repo at root, oldest first
+///
+/// Directories without a readable record of their origin are warned about and skipped
+pub(crate) fn list(root: &Path) -> Result<Vec<Item>, JrnError> {
+    let dir = dir(root);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut items = Vec::new();
+    for child in fs::read_dir(&dir)? {
+        let child = child?.path();
+        match read_item(&child) {
+            Ok(item) => items.push(item),
+            Err(e) => log::warn!("Skipping {} in the trash: {}", child.display(), e),
+        }
+    }
+    items.sort_by(|a, b| a.time.cmp(&b.time).then_with(|| a.id.cmp(&b.id)));
+    Ok(items)
+}
+
+fn read_item(dir: &Path) -> Result<Item, JrnError> {
+    let origin: Origin = serde_json::from_slice(&fs::read(dir.join(ORIGIN_FILE_NAME))?)?;
+    let time = DateTime::parse_from_rfc3339(&origin.time)
+        .map_err(|_| JrnError::InvalidTime(origin.time.clone()))?;
+    Ok(Item {
+        id: dir.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        dir: dir.to_path_buf(),
+        path: origin.path,
+        time,
+    })
+}
+
+/// Finds the entry in the trash whose id is id, or starts with it
+pub(crate) fn find(root: &Path, id: &str) -> Result<Item, JrnError> {
+    let mut items = list(root)?;
+    if let Some(exact) = items.iter().position(|item| item.id == id) {
+        return Ok(items.swap_remove(exact));
+    }
+    let mut matched = items.into_iter().filter(|item| item.id.starts_with(id));
+    match (matched.next(), matched.next()) {
+        (Some(item), None) => Ok(item),
+        (Some(_), Some(_)) => Err(JrnError::AmbiguousEntry(String::from(id))),
+        _ => Err(JrnError::EntryNotFound(String::from(id))),
+    }
+}
+
+/// Moves the entry in the trashed directory item back to path, its original path
+///
+/// Files that were put back since are never replaced
+pub(crate) fn restore(item: &Path, path: &Path) -> Result<(), JrnError> {
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    fs::create_dir_all(parent)?;
+    for child in fs::read_dir(item)? {
+        let child = child?.path();
+        let name = child.file_name().unwrap_or_default();
+        if name != ORIGIN_FILE_NAME {
+            rename_no_clobber(&child, &parent.join(name))?;
+        }
+    }
+    fs::remove_dir_all(item)?;
+    Ok(())
+}
+
+/// Parses an age such as 30d, 2w or 12h, a bare number is a number of days
+pub(crate) fn parse_age(age: &str) -> Option<Duration> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let n: i64 = age[..split].parse().ok()?;
+    match age[split..].trim() {
+        "" | "d" => Some(Duration::days(n)),
+        "w" => Some(Duration::weeks(n)),
+        "h" => Some(Duration::hours(n)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Location, TimeStamp};
+
+    #[test]
+    fn restores_entries_with_attachments() {
+        let root = tempfile::tempdir().unwrap();
+        let config = Settings::default();
+        let entry = JrnEntry {
+            creation_time: TimeStamp::from_ymdhm(2019, 10, 1, 9, 30).unwrap(),
+            location: Location::default(),
+            tags: vec![String::from("work")],
+            file_path: root.path().join("2019-10-01_0930-work"),
+        };
+        fs::write(&entry.file_path, "text").unwrap();
+        fs::create_dir(entry.attachment_dir(&config)).unwrap();
+
+        let dir = put(root.path(), &entry, &config).unwrap();
+        assert!(!entry.file_path.exists());
+        assert!(dir.join("2019-10-01_0930.attachments").is_dir());
+
+        let item = find(root.path(), &entry.id()[..4]).unwrap();
+        assert_eq!(item.dir, dir);
+        assert_eq!(item.path, PathBuf::from("2019-10-01_0930-work"));
+
+        restore(&item.dir, &entry.file_path).unwrap();
+        assert_eq!(fs::read_to_string(&entry.file_path).unwrap(), "text");
+        assert!(entry.attachment_dir(&config).is_dir());
+        assert!(!dir.exists());
+        assert_eq!(list(root.path()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("30d"), Some(Duration::days(30)));
+        assert_eq!(parse_age("2w"), Some(Duration::weeks(2)));
+        assert_eq!(parse_age("12"), Some(Duration::days(12)));
+        assert_eq!(parse_age("3 months"), None);
+        assert_eq!(parse_age("d"), None);
+    }
+}
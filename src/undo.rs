@@ -0,0 +1,139 @@
+//! A log of the commands that changed entries, so they can be undone
+//!
+//! Each command appends one json record to .jrn/undo.log, listing what it did, on a single line
+//!     {"time":"2019-10-01T09:30:00+02:00","command":"push-tag","operations":[
+//!         {"op":"rename","path":"2019-10-01_0930-work","from":"2019-10-01_0930"}
+//!     ]}
+//! Paths are relative to the root of the repo.
+//! jrn undo reverses the most recent records and drops them from the log
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use super::JrnError;
+use crate::config::JRN_DIR;
+
+static LOG_FILE_NAME: &str = "undo.log";
+
+/// A single change to an entry, with what is needed to reverse it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub(crate) enum Operation {
+    /// an entry was created at path
+    Create { path: PathBuf },
+    /// the entry now at path was renamed from the entry name from
+    Rename { path: PathBuf, from: String },
+    /// the entry at path was moved to the trash directory trashed, see [trash]
+    Remove { path: PathBuf, trashed: PathBuf },
+}
+
+/// The operations done by one command
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Record {
+    /// when the command ran, in rfc 3339
+    pub time: String,
+    /// the name of the command, such as push-tag
+    pub command: String,
+    pub operations: Vec<Operation>,
+}
+
+impl Record {
+    pub fn now(command: &str, operations: Vec<Operation>) -> Self {
+        Record {
+            time: Local::now().to_rfc3339(),
+            command: String::from(command),
+            operations,
+        }
+    }
+}
+
+/// The undo log of the repo at a root
+pub(crate) struct UndoLog {
+    path: PathBuf,
+}
+
+impl UndoLog {
+    pub fn at(root: &Path) -> Self {
+        UndoLog { path: root.join(JRN_DIR).join(LOG_FILE_NAME) }
+    }
+
+    /// Appends record to the log, records without operations are left out
+    pub fn append(&self, record: &Record) -> Result<(), JrnError> {
+        if record.operations.is_empty() {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Every record in the log, oldest first
+    ///
+    /// Lines that can not be read are warned about and skipped
+    pub fn read(&self) -> Result<Vec<Record>, JrnError> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut records = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(e) => {
+                    log::warn!("Skipping line {} of {}: {}", number + 1, self.path.display(), e)
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// Replaces the log with records
+    pub fn write(&self, records: &[Record]) -> Result<(), JrnError> {
+        let mut text = String::new();
+        for record in records {
+            text.push_str(&serde_json::to_string(record)?);
+            text.push('\n');
+        }
+        fs::write(&self.path, text)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_records() {
+        let root = tempfile::tempdir().unwrap();
+        let log = UndoLog::at(root.path());
+        assert_eq!(log.read().unwrap(), vec![]);
+
+        let rename = Operation::Rename {
+            path: PathBuf::from("2019-10-01_0930-a"),
+            from: String::from("2019-10-01_0930"),
+        };
+        log.append(&Record::now("push-tag", vec![rename.clone()])).unwrap();
+        log.append(&Record::now("retime", vec![])).unwrap();
+        let records = log.read().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].operations, vec![rename]);
+
+        log.write(&[]).unwrap();
+        assert_eq!(log.read().unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use super::{IgnorePatterns, JrnEntry, Settings, TagAliases};
+use crate::entry::is_attachment_dir;
+
+/// Walks the filesystem below root collecting every entry that is not ignored
+///
+/// Directories are read in parallel on the current rayon thread pool,
+/// the returned entries are sorted by creation time
+///
+/// The ignore patterns are only checked against paths below root,
+/// attachment directories are never searched for entries
+/// will not return Err if unable to read files in dir
+pub fn collect_entries(
+    root: &Path,
+    config: &Settings,
+    ignore: &IgnorePatterns,
+    aliases: &TagAliases,
+) -> Vec<JrnEntry> {
+    let mut entries = walk(root, ignore, &|path| JrnEntry::read_entry(path, config, aliases));
+    entries.par_sort();
+    entries
+}
+
+/// Walks like [collect_entries], reading entries from their file names alone,
+/// unsorted, see [JrnEntry::read_name]
+pub fn collect_names(
+    root: &Path,
+    config: &Settings,
+    ignore: &IgnorePatterns,
+    aliases: &TagAliases,
+) -> Vec<JrnEntry> {
+    walk(root, ignore, &|path| JrnEntry::read_name(path, config, aliases))
+}
+
+fn walk<R>(path: &Path, ignore: &IgnorePatterns, read: &R) -> Vec<JrnEntry>
+where
+    R: Fn(&Path) -> Option<JrnEntry> + Sync,
+{
+    if path.is_dir() {
+        match fs::read_dir(path) {
+            Ok(dir) => dir
+                .flatten()
+                .map(|file| file.path())
+                .filter(|child| !ignore.matches(child) && !is_attachment_dir(child))
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .flat_map_iter(|child| walk(&child, ignore, read))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    } else {
+        read(path).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    fn write_journal(root: &Path) {
+        for month in 1..=3 {
+            let dir = root.join(format!("2019-{:02}", month));
+            fs::create_dir(&dir).unwrap();
+            for day in 1..=9 {
+                let name = format!("2019-{:02}-{:02}_1200-work_day{}", month, day, day % 3);
+                File::create(dir.join(name)).unwrap();
+            }
+        }
+        fs::create_dir(root.join(".git")).unwrap();
+        File::create(root.join(".git").join("2019-01-01_1200-hidden")).unwrap();
+    }
+
+    fn collect_with_threads(root: &Path, threads: usize) -> Vec<JrnEntry> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let config = Settings::default();
+        let ignore = IgnorePatterns::find_or_default();
+        let aliases = TagAliases::default();
+        pool.install(|| collect_entries(root, &config, &ignore, &aliases))
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let dir = tempfile::tempdir().unwrap();
+        write_journal(dir.path());
+
+        let serial = collect_with_threads(dir.path(), 1);
+        let parallel = collect_with_threads(dir.path(), 4);
+        assert_eq!(serial.len(), 27);
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn honours_ignore_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        write_journal(dir.path());
+
+        let entries = collect_with_threads(dir.path(), 4);
+        assert!(entries.iter().all(|e| !e.file_path_str().contains(".git")));
+    }
+
+    #[test]
+    fn entries_are_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        write_journal(dir.path());
+
+        let entries = collect_with_threads(dir.path(), 4);
+        assert!(entries.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn collects_below_hidden_roots() {
+        let dir = tempfile::Builder::new().prefix(".journal").tempdir().unwrap();
+        write_journal(dir.path());
+
+        assert_eq!(collect_with_threads(dir.path(), 4).len(), 27);
+    }
+}